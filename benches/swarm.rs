@@ -0,0 +1,231 @@
+//! Per-tick cost of the swarm at scale: a headless app with a player, a ring of hatcheries
+//! and N swarmlings packed in a grid, timing one system or schedule at a time.
+//!
+//! Run with `cargo bench --bench swarm`, or `cargo bench --bench swarm -- swarmling_move`
+//! for a single group.
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::f32::consts::TAU;
+use std::time::{Duration, Instant};
+use swarmy::arena::ArenaSource;
+use swarmy::balance::GameBalance;
+use swarmy::player::{Invulnerable, Player};
+use swarmy::projectile::{projectile_collision, Projectile};
+use swarmy::swarm::*;
+
+const SWARM_SIZES: [usize; 3] = [1_000, 5_000, 20_000];
+const HATCHERIES: usize = 16;
+const HATCHERY_RING_RADIUS: f32 = 600.0;
+/// Projectiles fired into the swarm per iteration of the projectile collision bench.
+const VOLLEY: usize = 64;
+/// Gap between swarmling centers, wide enough that none start out touching.
+const GRID_SPACING: f32 = 12.0;
+/// The grid starts this far right of the player, so the swarm doesn't swamp it.
+const GRID_OFFSET: f32 = 200.0;
+
+/// Marks the swarmlings placed by [`swarm_app`], so benches can clear out the ones they add.
+#[derive(Component)]
+struct Baseline;
+
+fn swarm_app(swarmlings: usize) -> App {
+    let mut app = swarmy::headless_app(42);
+    app.insert_resource(ArenaSource::Empty);
+    // Runs the startup schedule, which spawns the player at the origin
+    app.update();
+
+    let assets = app.world().resource::<AssetServer>().clone();
+    let balance = app.world().resource::<GameBalance>().clone();
+    let world = app.world_mut();
+    let player = world.query_filtered::<Entity, With<Player>>().single(world);
+    world.entity_mut(player).insert(Invulnerable);
+
+    for i in 0..HATCHERIES {
+        let position = Vec2::from_angle(i as f32 / HATCHERIES as f32 * TAU) * HATCHERY_RING_RADIUS;
+        world.spawn(hatchery_bundle(
+            &assets,
+            position,
+            HatcherySettings::default(),
+            &balance.hatchery,
+        ));
+    }
+
+    let side = (swarmlings as f32).sqrt().ceil() as usize;
+    let half_height = side as f32 * GRID_SPACING / 2.;
+    let batch: Vec<_> = (0..swarmlings)
+        .map(|i| {
+            let position = Vec2::new(
+                GRID_OFFSET + (i % side) as f32 * GRID_SPACING,
+                (i / side) as f32 * GRID_SPACING - half_height,
+            );
+            (
+                swarmling_bundle(&assets, i as i32, &balance.swarmling),
+                ExternalImpulse::default(),
+                Transform::from_translation(position.extend(0.)),
+                Baseline,
+            )
+        })
+        .collect();
+    world.spawn_batch(batch);
+
+    // Lets physics pick up the new bodies before anything is timed
+    app.update();
+    app.update();
+    app
+}
+
+fn swarm_group(c: &mut Criterion, name: &str, mut bench: impl FnMut(&mut App, u64) -> Duration) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(20);
+    for swarmlings in SWARM_SIZES {
+        let mut app = swarm_app(swarmlings);
+        group.throughput(Throughput::Elements(swarmlings as u64));
+        group.bench_function(BenchmarkId::from_parameter(swarmlings), |b| {
+            b.iter_custom(|iters| bench(&mut app, iters));
+        });
+    }
+    group.finish();
+}
+
+/// Times `iters` runs of `run`, leaving out `reset` between them.
+fn timed(
+    app: &mut App,
+    iters: u64,
+    mut run: impl FnMut(&mut World),
+    mut reset: impl FnMut(&mut World),
+) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        let start = Instant::now();
+        run(app.world_mut());
+        total += start.elapsed();
+        reset(app.world_mut());
+    }
+    total
+}
+
+fn despawn_added_swarmlings(world: &mut World) {
+    let added: Vec<Entity> = world
+        .query_filtered::<Entity, (With<Swarmling>, Without<Baseline>)>()
+        .iter(world)
+        .collect();
+    for entity in added {
+        world.despawn(entity);
+    }
+}
+
+/// A whole frame: the gameplay chain, physics and everything else in the headless app.
+/// Swarmlings the hatcheries spawn are cleared out between frames, so the swarm stays at
+/// its benched size.
+fn full_tick(c: &mut Criterion) {
+    swarm_group(c, "tick", |app, iters| {
+        let mut total = Duration::ZERO;
+        for _ in 0..iters {
+            let start = Instant::now();
+            app.update();
+            total += start.elapsed();
+            despawn_added_swarmlings(app.world_mut());
+        }
+        total
+    });
+}
+
+fn swarmling_move_bench(c: &mut Criterion) {
+    swarm_group(c, "swarmling_move", |app, iters| {
+        timed(
+            app,
+            iters,
+            |world| world.run_system_cached(swarmling_move).unwrap(),
+            |_| {},
+        )
+    });
+}
+
+/// One physics step, run the way the fixed loop runs it.
+fn physics(c: &mut Criterion) {
+    swarm_group(c, "physics", |app, iters| {
+        timed(
+            app,
+            iters,
+            |world| world.run_schedule(FixedPostUpdate),
+            |_| {},
+        )
+    });
+}
+
+/// A volley of projectiles, each sitting on a swarmling, once physics has reported the
+/// contacts.
+fn projectile_collision_bench(c: &mut Criterion) {
+    swarm_group(c, "projectile_collision", |app, iters| {
+        let mut total = Duration::ZERO;
+        for _ in 0..iters {
+            let world = app.world_mut();
+            let balance = world.resource::<GameBalance>().projectile.clone();
+            let targets: Vec<Vec3> = world
+                .query_filtered::<&Transform, With<Swarmling>>()
+                .iter(world)
+                .step_by(7)
+                .take(VOLLEY)
+                .map(|transform| transform.translation)
+                .collect();
+            for translation in targets {
+                world.spawn((
+                    Projectile::new(&balance),
+                    Transform::from_translation(translation),
+                ));
+            }
+            world.run_schedule(FixedPostUpdate);
+
+            total += timed(
+                app,
+                1,
+                |world| world.run_system_cached(projectile_collision).unwrap(),
+                |world| {
+                    let missed: Vec<Entity> = world
+                        .query_filtered::<Entity, With<Projectile>>()
+                        .iter(world)
+                        .collect();
+                    for entity in missed {
+                        world.despawn(entity);
+                    }
+                },
+            );
+        }
+        total
+    });
+}
+
+/// Every hatchery spawning a swarmling in the same tick, including applying the spawns.
+fn spawn_path(c: &mut Criterion) {
+    swarm_group(c, "swarmling_spawn", |app, iters| {
+        ready_spawners(app.world_mut());
+        timed(
+            app,
+            iters,
+            |world| world.run_system_cached(swarmling_spawn).unwrap(),
+            |world| {
+                despawn_added_swarmlings(world);
+                ready_spawners(world);
+            },
+        )
+    });
+}
+
+/// Leaves every spawner due to fire on its next tick.
+fn ready_spawners(world: &mut World) {
+    for mut spawner in world.query::<&mut SwarmSpawner>().iter_mut(world) {
+        let duration = spawner.spawn_timer.duration();
+        spawner.spawn_timer.set_elapsed(duration);
+    }
+}
+
+criterion_group!(
+    benches,
+    full_tick,
+    swarmling_move_bench,
+    physics,
+    projectile_collision_bench,
+    spawn_path
+);
+criterion_main!(benches);
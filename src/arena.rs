@@ -0,0 +1,278 @@
+use avian2d::prelude::*;
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::utils::HashMap;
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+
+use crate::balance::GameBalance;
+use crate::consts::*;
+use crate::level::PendingLevel;
+use crate::swarm::{hatchery_bundle, HatcherySettings};
+
+/// Where the arena comes from: generated from the run seed, or a Tiled map asset
+/// passed with `--level <path>`.
+#[derive(Resource, Debug, Clone, Default)]
+pub enum ArenaSource {
+    #[default]
+    Generated,
+    Level(String),
+    /// Nothing at all, for tests that place their own entities
+    Empty,
+}
+
+impl ArenaSource {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut source = ArenaSource::Generated;
+        while let Some(arg) = args.next() {
+            if arg == "--level" {
+                if let Some(path) = args.next() {
+                    source = ArenaSource::Level(path);
+                }
+            }
+        }
+        source
+    }
+}
+
+/// Everything needed to spawn one generated arena.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaLayout {
+    /// Ground tile centers with a 0..1 shade
+    pub ground: Vec<(Vec2, f32)>,
+    /// Rock centers and radii
+    pub rocks: Vec<(Vec2, f32)>,
+    pub hatcheries: Vec<Vec2>,
+    pub player_spawn: Vec2,
+}
+
+#[derive(Component)]
+pub struct ArenaGround;
+
+#[derive(Component)]
+pub struct Rock;
+
+/// Lays out a square arena from `seed`: noise-shaded ground, rock clusters where the noise
+/// is high, a border of rocks, and hatchery spots in clearings away from the player spawn.
+pub fn generate_arena(seed: u64) -> ArenaLayout {
+    let mut rng = fastrand::Rng::with_seed(seed);
+
+    let mut rock_noise = FastNoiseLite::with_seed(seed as i32);
+    rock_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    rock_noise.set_frequency(Some(ARENA_ROCK_NOISE_FREQUENCY));
+
+    let mut ground_noise = FastNoiseLite::with_seed(seed.wrapping_add(1) as i32);
+    ground_noise.set_noise_type(Some(NoiseType::Perlin));
+    ground_noise.set_frequency(Some(ARENA_GROUND_NOISE_FREQUENCY));
+
+    let player_spawn = Vec2::ZERO;
+    let half_extent = ARENA_TILES as f32 * ARENA_TILE_SIZE * 0.5;
+
+    // Hatchery spots, spaced out from the player and from each other
+    let mut hatcheries: Vec<Vec2> = Vec::new();
+    let margin = ARENA_TILE_SIZE * 3.;
+    for _ in 0..ARENA_HATCHERY_PLACEMENT_ATTEMPTS {
+        if hatcheries.len() >= ARENA_HATCHERY_COUNT {
+            break;
+        }
+        let candidate =
+            Vec2::new(rng.f32() * 2. - 1., rng.f32() * 2. - 1.) * (half_extent - margin);
+        let far_from_player =
+            candidate.distance(player_spawn) >= ARENA_HATCHERY_MIN_PLAYER_DISTANCE;
+        let far_from_others = hatcheries
+            .iter()
+            .all(|other| candidate.distance(*other) >= ARENA_HATCHERY_MIN_SPACING);
+        if far_from_player && far_from_others {
+            hatcheries.push(candidate);
+        }
+    }
+
+    let in_clearing = |position: Vec2| {
+        position.distance(player_spawn) < ARENA_PLAYER_CLEARING_RADIUS
+            || hatcheries
+                .iter()
+                .any(|hatchery| position.distance(*hatchery) < ARENA_HATCHERY_CLEARING_RADIUS)
+    };
+
+    let mut ground = Vec::new();
+    let mut rocks = Vec::new();
+    for x in 0..ARENA_TILES {
+        for y in 0..ARENA_TILES {
+            let position = Vec2::new(
+                (x as f32 + 0.5) * ARENA_TILE_SIZE - half_extent,
+                (y as f32 + 0.5) * ARENA_TILE_SIZE - half_extent,
+            );
+            let shade = (ground_noise.get_noise_2d(x as f32, y as f32) + 1.) * 0.5;
+            ground.push((position, shade));
+
+            let border = x == 0 || y == 0 || x == ARENA_TILES - 1 || y == ARENA_TILES - 1;
+            if border {
+                rocks.push((position, ARENA_TILE_SIZE * ARENA_BORDER_ROCK_SIZE));
+                continue;
+            }
+            let density = rock_noise.get_noise_2d(x as f32, y as f32);
+            if density > ARENA_ROCK_THRESHOLD && !in_clearing(position) {
+                let jitter = Vec2::new(rng.f32() - 0.5, rng.f32() - 0.5) * ARENA_TILE_SIZE * 0.3;
+                let size = ARENA_ROCK_SIZES[rng.usize(..ARENA_ROCK_SIZES.len())];
+                let radius = ARENA_TILE_SIZE * size;
+                rocks.push((position + jitter, radius));
+            }
+        }
+    }
+
+    ArenaLayout {
+        ground,
+        rocks,
+        hatcheries,
+        player_spawn,
+    }
+}
+
+/// Generates the arena for this run's [`GameRng`] seed and spawns it, or starts loading
+/// the level file instead.
+pub fn spawn_arena(
+    mut commands: Commands,
+    source: Res<ArenaSource>,
+    rng: Res<GameRng>,
+    assets: Res<AssetServer>,
+    balance: Res<GameBalance>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    match source.as_ref() {
+        ArenaSource::Generated => {}
+        ArenaSource::Level(path) => {
+            info!("Loading level {}", path);
+            commands.insert_resource(PendingLevel(assets.load(path.clone())));
+            return;
+        }
+        ArenaSource::Empty => return,
+    }
+
+    let layout = generate_arena(rng.seed);
+    info!(
+        "Generated arena with seed {}: {} rocks, {} hatcheries",
+        rng.seed,
+        layout.rocks.len(),
+        layout.hatcheries.len()
+    );
+
+    spawn_terrain(&mut commands, &layout, &mut meshes, &mut materials);
+
+    for position in layout.hatcheries.iter() {
+        commands.spawn(hatchery_bundle(
+            &assets,
+            *position,
+            HatcherySettings::default(),
+            &balance.hatchery,
+        ));
+    }
+}
+
+/// Spawns the ground and rocks of `layout`, without its hatcheries.
+pub fn spawn_terrain(
+    commands: &mut Commands,
+    layout: &ArenaLayout,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    commands.spawn((
+        ArenaGround,
+        Name::new("Ground"),
+        Mesh2d(meshes.add(ground_mesh(&layout.ground))),
+        MeshMaterial2d(materials.add(ColorMaterial::default())),
+        Transform::from_xyz(0., 0., ARENA_GROUND_Z),
+    ));
+
+    let rock_material = materials.add(ARENA_ROCK_COLOR);
+    let mut rock_meshes: HashMap<u32, Handle<Mesh>> = HashMap::default();
+    for (position, radius) in layout.rocks.iter() {
+        let mesh = rock_meshes
+            .entry(radius.to_bits())
+            .or_insert_with(|| meshes.add(Circle::new(*radius)));
+        commands.spawn((
+            Rock,
+            Name::new("Rock"),
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(rock_material.clone()),
+            Transform::from_translation(position.extend(0.)),
+            RigidBody::Static,
+            Collider::circle(*radius),
+        ));
+    }
+}
+
+/// One quad per ground tile, shaded through vertex colors, so the whole floor is a single
+/// mesh instead of a sprite per tile.
+fn ground_mesh(ground: &[(Vec2, f32)]) -> Mesh {
+    let dark = LinearRgba::from(ARENA_GROUND_DARK);
+    let light = LinearRgba::from(ARENA_GROUND_LIGHT);
+    let corners = [
+        Vec2::new(-1., -1.),
+        Vec2::new(1., -1.),
+        Vec2::new(1., 1.),
+        Vec2::new(-1., 1.),
+    ]
+    .map(|corner| corner * ARENA_TILE_SIZE * 0.5);
+    let mut positions = Vec::with_capacity(ground.len() * 4);
+    let mut colors = Vec::with_capacity(ground.len() * 4);
+    let mut indices = Vec::with_capacity(ground.len() * 6);
+    for (center, shade) in ground.iter() {
+        let first = positions.len() as u32;
+        for corner in corners {
+            positions.push((*center + corner).extend(0.).to_array());
+        }
+        colors.extend([dark.mix(&light, *shade).to_f32_array(); 4]);
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hatchery colliders are 84 px squares, so this is half their diagonal.
+    const HATCHERY_RADIUS: f32 = 42. * std::f32::consts::SQRT_2;
+
+    #[test]
+    fn same_seed_gives_same_layout() {
+        for seed in [0, 42, 9_001] {
+            assert_eq!(generate_arena(seed), generate_arena(seed));
+        }
+        assert_ne!(generate_arena(1).rocks, generate_arena(2).rocks);
+    }
+
+    #[test]
+    fn hatcheries_avoid_rocks_and_spawn() {
+        for seed in 0..32 {
+            let layout = generate_arena(seed);
+            assert!(!layout.hatcheries.is_empty(), "seed {} placed no hatcheries", seed);
+            for hatchery in layout.hatcheries.iter() {
+                let from_spawn = hatchery.distance(layout.player_spawn);
+                assert!(
+                    from_spawn >= ARENA_PLAYER_CLEARING_RADIUS + HATCHERY_RADIUS,
+                    "seed {}: hatchery at {} is in the spawn area",
+                    seed,
+                    hatchery
+                );
+                for (rock, radius) in layout.rocks.iter() {
+                    assert!(
+                        hatchery.distance(*rock) > radius + HATCHERY_RADIUS,
+                        "seed {}: hatchery at {} overlaps the rock at {}",
+                        seed,
+                        hatchery,
+                        rock
+                    );
+                }
+            }
+        }
+    }
+}
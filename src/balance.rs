@@ -0,0 +1,300 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::player::Player;
+use crate::weapon::Weapon;
+
+/// Default balance file, relative to the assets folder.
+pub const BALANCE_ASSET_PATH: &str = "balance/default.balance.ron";
+
+/// Loads the [`GameBalance`] from a `.balance.ron` asset and copies it into the resource
+/// every system reads. With the `dev` feature the file is watched, so saving it retunes
+/// the running game.
+pub struct BalancePlugin;
+
+impl Plugin for BalancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GameBalance>()
+            .init_asset_loader::<GameBalanceLoader>()
+            .register_type::<GameBalance>()
+            .init_resource::<GameBalance>()
+            .init_resource::<BalanceSource>()
+            .add_systems(Startup, load_balance)
+            .add_systems(
+                Update,
+                (
+                    apply_balance.run_if(resource_exists::<BalanceHandle>),
+                    retune_weapons.run_if(resource_changed::<GameBalance>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Every gameplay tuning number. Fields missing from a balance file keep their defaults.
+#[derive(Asset, Resource, Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct GameBalance {
+    pub player: PlayerBalance,
+    pub weapon: WeaponBalance,
+    pub projectile: ProjectileBalance,
+    pub swarmling: SwarmlingBalance,
+    pub hatchery: HatcheryBalance,
+    pub score: ScoreBalance,
+}
+
+impl GameBalance {
+    /// Reads a balance file from disk, outside of the asset server.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PlayerBalance {
+    pub health: f32,
+    pub movement_speed: f32,
+    pub dash_speed: f32,
+    pub dash_duration: f32,
+    pub dash_cooldown: f32,
+}
+
+impl Default for PlayerBalance {
+    fn default() -> Self {
+        PlayerBalance {
+            health: 1.0,
+            movement_speed: 100.0,
+            dash_speed: 750.0,
+            dash_duration: 0.3,
+            dash_cooldown: 1.0,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WeaponBalance {
+    /// Maximum deviation from the aim direction, in radians
+    pub spread: f32,
+    pub magazine_size: u32,
+    pub reload_time: f32,
+}
+
+impl Default for WeaponBalance {
+    fn default() -> Self {
+        WeaponBalance {
+            spread: 0.06,
+            magazine_size: 12,
+            reload_time: 1.2,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ProjectileBalance {
+    pub speed: f32,
+    pub range: f32,
+    pub damage: f32,
+}
+
+impl Default for ProjectileBalance {
+    fn default() -> Self {
+        ProjectileBalance {
+            speed: 500.0,
+            range: 750.0,
+            damage: 1.0,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SwarmlingBalance {
+    pub health: f32,
+    pub speed: f32,
+    /// Impulse a swarmling leaves its hatchery with
+    pub launch_speed: f32,
+    pub collision_damage: f32,
+    pub points: u32,
+    /// Distance at which a swarmling notices the player
+    pub perception_radius: f32,
+    /// Distance within which other swarmlings count as neighbors
+    pub neighbor_radius: f32,
+}
+
+impl Default for SwarmlingBalance {
+    fn default() -> Self {
+        SwarmlingBalance {
+            health: 2.0,
+            speed: 50.0,
+            launch_speed: 1500.0,
+            collision_damage: 1.0,
+            points: 10,
+            perception_radius: 200.0,
+            neighbor_radius: 40.0,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HatcheryBalance {
+    pub health: f32,
+    /// Seconds between swarmlings, unless the level overrides it
+    pub spawn_interval: f32,
+    /// Spawn interval multiplier once the hatchery is critical
+    pub panic_spawn_factor: f32,
+    /// Health fraction below which the hatchery counts as damaged
+    pub damaged_fraction: f32,
+    /// Health fraction below which the hatchery counts as critical
+    pub critical_fraction: f32,
+    pub destruction_time: f32,
+    pub points: u32,
+}
+
+impl Default for HatcheryBalance {
+    fn default() -> Self {
+        HatcheryBalance {
+            health: 10.0,
+            spawn_interval: 1.0,
+            panic_spawn_factor: 0.4,
+            damaged_fraction: 0.75,
+            critical_fraction: 0.5,
+            destruction_time: 1.5,
+            points: 500,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ScoreBalance {
+    /// Seconds without a kill before the combo breaks
+    pub combo_window: f32,
+    /// Kills per multiplier step
+    pub combo_step: u32,
+    pub max_multiplier: u32,
+}
+
+impl Default for ScoreBalance {
+    fn default() -> Self {
+        ScoreBalance {
+            combo_window: 2.0,
+            combo_step: 5,
+            max_multiplier: 8,
+        }
+    }
+}
+
+/// Where the [`GameBalance`] comes from.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub enum BalanceSource {
+    /// Loaded and hot-reloaded from this asset path
+    Asset(String),
+    /// Whatever [`GameBalance`] resource was inserted, e.g. by the batch simulator
+    Fixed,
+}
+
+impl Default for BalanceSource {
+    fn default() -> Self {
+        BalanceSource::Asset(String::from(BALANCE_ASSET_PATH))
+    }
+}
+
+/// Keeps the balance asset loaded so changes on disk are picked up.
+#[derive(Resource)]
+pub struct BalanceHandle(pub Handle<GameBalance>);
+
+#[derive(Default)]
+pub struct GameBalanceLoader;
+
+impl AssetLoader for GameBalanceLoader {
+    type Asset = GameBalance;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["balance.ron"]
+    }
+}
+
+/// Reads the balance file right away, so the player and arena spawned at startup are
+/// tuned by it, then hands it to the asset server to watch for changes.
+pub fn load_balance(
+    mut commands: Commands,
+    source: Res<BalanceSource>,
+    assets: Res<AssetServer>,
+    mut balance: ResMut<GameBalance>,
+) {
+    let BalanceSource::Asset(path) = source.as_ref() else {
+        return;
+    };
+    let file = FileAssetReader::get_base_path().join("assets").join(path);
+    match GameBalance::load(&file) {
+        Ok(loaded) => *balance = loaded,
+        Err(err) => warn!(
+            "Could not load game balance from {}: {}",
+            file.display(),
+            err
+        ),
+    }
+    commands.insert_resource(BalanceHandle(assets.load(path.clone())));
+}
+
+fn apply_balance(
+    mut asset_event_reader: EventReader<AssetEvent<GameBalance>>,
+    balances: Res<Assets<GameBalance>>,
+    handle: Res<BalanceHandle>,
+    mut balance: ResMut<GameBalance>,
+) {
+    for event in asset_event_reader.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if *id != handle.0.id() {
+            continue;
+        }
+        if let Some(loaded) = balances.get(*id) {
+            *balance = loaded.clone();
+            info!("Applied game balance");
+        }
+    }
+}
+
+/// Weapon stats live on the component, so push retuned values to the player's weapon.
+fn retune_weapons(balance: Res<GameBalance>, mut q_weapon: Query<&mut Weapon, With<Player>>) {
+    for mut weapon in q_weapon.iter_mut() {
+        weapon.retune(&balance.weapon);
+    }
+}
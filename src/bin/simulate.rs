@@ -0,0 +1,315 @@
+//! Runs seeded headless matches in parallel and writes per-run metrics to CSV.
+//!
+//! ```text
+//! cargo run --release --bin simulate -- --runs 50 --player bot --player turret --out balance.csv
+//! ```
+//!
+//! Every `--player` is one configuration, played `--runs` times with seeds counting up
+//! from `--seed`. Matches end when the player dies, the level is cleared, or after
+//! `--max-time` simulated seconds. `--balance` plays every match with a balance file
+//! other than the game's default one.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use swarmy::arena::ArenaSource;
+use swarmy::balance::{BalanceSource, GameBalance};
+use swarmy::bot::Autopilot;
+use swarmy::consts::*;
+use swarmy::input::{PlayerAimEvent, PlayerAttackEvent};
+use swarmy::player::{player_aim, player_death, PlayerDamageEvent};
+use swarmy::projectile::ProjectileHitEvent;
+use swarmy::score::Score;
+use swarmy::swarm::{Hatchery, HatcheryDestroyed, Swarmling};
+
+/// Turn rate of the scripted turret player, in radians per second.
+const TURRET_TURN_RATE: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlayerKind {
+    /// The [`Autopilot`] bot
+    Bot,
+    /// Stands still, sweeps its aim around and fires constantly
+    Turret,
+}
+
+impl PlayerKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bot" => Some(PlayerKind::Bot),
+            "turret" => Some(PlayerKind::Turret),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PlayerKind::Bot => "bot",
+            PlayerKind::Turret => "turret",
+        }
+    }
+}
+
+struct Options {
+    runs: usize,
+    seed: u64,
+    max_time: f32,
+    threads: usize,
+    out: PathBuf,
+    players: Vec<PlayerKind>,
+    level: Option<String>,
+    balance: Option<GameBalance>,
+}
+
+impl Options {
+    fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            runs: 10,
+            seed: 0,
+            max_time: 300.,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            out: PathBuf::from("simulation.csv"),
+            players: Vec::new(),
+            level: None,
+            balance: None,
+        };
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--runs" => options.runs = value()?.parse().map_err(|e| format!("--runs: {}", e))?,
+                "--seed" => options.seed = value()?.parse().map_err(|e| format!("--seed: {}", e))?,
+                "--max-time" => {
+                    options.max_time = value()?.parse().map_err(|e| format!("--max-time: {}", e))?
+                }
+                "--threads" => {
+                    options.threads = value()?.parse().map_err(|e| format!("--threads: {}", e))?
+                }
+                "--out" => options.out = PathBuf::from(value()?),
+                "--player" => {
+                    let name = value()?;
+                    let player = PlayerKind::parse(&name)
+                        .ok_or(format!("unknown player {:?}, expected bot or turret", name))?;
+                    options.players.push(player);
+                }
+                "--level" => options.level = Some(value()?),
+                "--balance" => {
+                    let path = value()?;
+                    let balance = GameBalance::load(&path)
+                        .map_err(|e| format!("--balance {}: {}", path, e))?;
+                    options.balance = Some(balance);
+                }
+                _ => return Err(format!("unknown argument {:?}", arg)),
+            }
+        }
+        if options.players.is_empty() {
+            options.players.push(PlayerKind::Bot);
+        }
+        options.threads = options.threads.max(1);
+        Ok(options)
+    }
+}
+
+/// Metrics the score doesn't already track, collected every fixed tick.
+#[derive(Resource, Default)]
+struct RunMetrics {
+    elapsed: f32,
+    damage_taken: f32,
+    peak_swarm: usize,
+    hatchery_first_hit: HashMap<Entity, f32>,
+    /// Seconds from the first hit on a hatchery until it was destroyed
+    hatchery_kill_times: Vec<f32>,
+}
+
+fn track_metrics(
+    mut metrics: ResMut<RunMetrics>,
+    mut player_damage_event_reader: EventReader<PlayerDamageEvent>,
+    mut projectile_hit_event_reader: EventReader<ProjectileHitEvent>,
+    mut hatchery_destroyed_event_reader: EventReader<HatcheryDestroyed>,
+    q_swarmling: Query<(), With<Swarmling>>,
+    q_hatchery: Query<(), With<Hatchery>>,
+    time: Res<Time>,
+) {
+    metrics.elapsed += time.delta_secs();
+    let elapsed = metrics.elapsed;
+    for event in player_damage_event_reader.read() {
+        let PlayerDamageEvent::Collision(damage) = event;
+        metrics.damage_taken += damage;
+    }
+    metrics.peak_swarm = metrics.peak_swarm.max(q_swarmling.iter().count());
+    for hit in projectile_hit_event_reader.read() {
+        if q_hatchery.contains(hit.target) {
+            metrics.hatchery_first_hit.entry(hit.target).or_insert(elapsed);
+        }
+    }
+    for destroyed in hatchery_destroyed_event_reader.read() {
+        if let Some(first_hit) = metrics.hatchery_first_hit.remove(&destroyed.entity) {
+            metrics.hatchery_kill_times.push(elapsed - first_hit);
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct Turret {
+    angle: f32,
+    fire_cooldown: f32,
+}
+
+fn turret_input(
+    mut turret: ResMut<Turret>,
+    mut aim_event_writer: EventWriter<PlayerAimEvent>,
+    mut attack_event_writer: EventWriter<PlayerAttackEvent>,
+    time: Res<Time>,
+) {
+    turret.angle += TURRET_TURN_RATE * time.delta_secs();
+    aim_event_writer.send(PlayerAimEvent(Vec2::from_angle(turret.angle)));
+    turret.fire_cooldown -= time.delta_secs();
+    if turret.fire_cooldown <= 0. {
+        attack_event_writer.send(PlayerAttackEvent::PrimaryFire);
+        turret.fire_cooldown = BOT_FIRE_INTERVAL;
+    }
+}
+
+struct RunResult {
+    player: PlayerKind,
+    seed: u64,
+    outcome: &'static str,
+    time_survived: f32,
+    kills: u32,
+    damage_taken: f32,
+    peak_swarm: usize,
+    hatcheries: u32,
+    hatchery_time_to_kill: Option<f32>,
+}
+
+fn simulate(player: PlayerKind, seed: u64, options: &Options) -> RunResult {
+    let mut app = swarmy::headless_app(seed);
+    if let Some(level) = &options.level {
+        app.insert_resource(ArenaSource::Level(level.clone()));
+    }
+    if let Some(balance) = &options.balance {
+        app.insert_resource(balance.clone()).insert_resource(BalanceSource::Fixed);
+    }
+    app.init_resource::<RunMetrics>()
+        .add_systems(FixedUpdate, track_metrics.after(player_death));
+    match player {
+        PlayerKind::Bot => {
+            app.insert_resource(Autopilot::enabled());
+        }
+        PlayerKind::Turret => {
+            app.init_resource::<Turret>()
+                .add_systems(FixedUpdate, turret_input.before(player_aim));
+        }
+    }
+
+    let ticks = (options.max_time / Time::<Fixed>::default().timestep().as_secs_f32()) as usize;
+    let mut outcome = "timeout";
+    for _ in 0..ticks {
+        app.update();
+        match app.world().resource::<State<AppState>>().get() {
+            AppState::Victory => {
+                outcome = "cleared";
+                break;
+            }
+            AppState::GameOver => {
+                outcome = "died";
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let score = app.world().resource::<Score>();
+    let metrics = app.world().resource::<RunMetrics>();
+    let kill_times = &metrics.hatchery_kill_times;
+    RunResult {
+        player,
+        seed,
+        outcome,
+        time_survived: score.duration,
+        kills: score.kills,
+        damage_taken: metrics.damage_taken,
+        peak_swarm: metrics.peak_swarm,
+        hatcheries: score.hatcheries,
+        hatchery_time_to_kill: (!kill_times.is_empty())
+            .then(|| kill_times.iter().sum::<f32>() / kill_times.len() as f32),
+    }
+}
+
+fn to_csv(results: &[RunResult]) -> String {
+    let mut csv = String::from(
+        "player,seed,outcome,time_survived,kills,damage_taken,peak_swarm,hatcheries_destroyed,hatchery_time_to_kill\n",
+    );
+    for result in results {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{:.2},{},{:.2},{},{},{}",
+            result.player.name(),
+            result.seed,
+            result.outcome,
+            result.time_survived,
+            result.kills,
+            result.damage_taken,
+            result.peak_swarm,
+            result.hatcheries,
+            result
+                .hatchery_time_to_kill
+                .map_or(String::new(), |seconds| format!("{:.2}", seconds)),
+        );
+    }
+    csv
+}
+
+fn main() {
+    let options = match Options::from_args(std::env::args()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!(
+                "usage: simulate [--runs N] [--seed S] [--max-time SECONDS] [--threads N] \
+                 [--player bot|turret]... [--level PATH] [--balance PATH] [--out PATH]"
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let jobs: Vec<(PlayerKind, u64)> = options
+        .players
+        .iter()
+        .flat_map(|player| (0..options.runs as u64).map(|run| (*player, options.seed + run)))
+        .collect();
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(jobs.len()));
+    thread::scope(|scope| {
+        for _ in 0..options.threads.min(jobs.len()) {
+            scope.spawn(|| {
+                while let Some((player, seed)) = jobs.get(next_job.fetch_add(1, Ordering::Relaxed)) {
+                    let result = simulate(*player, *seed, &options);
+                    println!(
+                        "{} seed {}: {} after {:.1}s, {} kills",
+                        player.name(),
+                        seed,
+                        result.outcome,
+                        result.time_survived,
+                        result.kills
+                    );
+                    results.lock().unwrap().push(result);
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|result| (result.player.name(), result.seed));
+    if let Err(err) = fs::write(&options.out, to_csv(&results)) {
+        eprintln!("Could not write {}: {}", options.out.display(), err);
+        std::process::exit(1);
+    }
+    println!("Wrote {} runs to {}", results.len(), options.out.display());
+}
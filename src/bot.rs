@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+
+use crate::balance::GameBalance;
+use crate::consts::*;
+use crate::input::*;
+use crate::player::{player_aim, Dash, Player};
+use crate::swarm::{Hatchery, Swarmling};
+
+/// Drives the player with the same movement, aim and attack events a human produces,
+/// for unattended soak tests and balance runs. Runs in `FixedUpdate`, so it works
+/// headless and stays deterministic for a given seed.
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autopilot>().add_systems(
+            FixedUpdate,
+            bot_input.run_if(autopilot_enabled).before(player_aim),
+        );
+    }
+}
+
+/// Whether the bot is playing, and its memory between ticks. Enabled with `--bot`.
+#[derive(Resource, Default, Debug)]
+pub struct Autopilot {
+    pub enabled: bool,
+    fire_cooldown: f32,
+    last_position: Option<Vec2>,
+}
+
+impl Autopilot {
+    pub fn enabled() -> Self {
+        Autopilot {
+            enabled: true,
+            ..default()
+        }
+    }
+
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        Autopilot {
+            enabled: args.into_iter().any(|arg| arg == "--bot"),
+            ..default()
+        }
+    }
+}
+
+/// Run condition for the bot, which replaces the live input systems.
+pub fn autopilot_enabled(autopilot: Res<Autopilot>) -> bool {
+    autopilot.enabled
+}
+
+/// Kites away from nearby swarmlings, dashes out when cornered, and shoots the nearest
+/// threat, or the nearest hatchery when none are close.
+pub fn bot_input(
+    mut autopilot: ResMut<Autopilot>,
+    q_player: Query<(&Transform, &Dash), With<Player>>,
+    q_swarmling: Query<&Transform, With<Swarmling>>,
+    q_hatchery: Query<&Transform, (With<Hatchery>, Without<Dying>)>,
+    mut movement_event_writer: EventWriter<PlayerMovementEvent>,
+    mut aim_event_writer: EventWriter<PlayerAimEvent>,
+    mut attack_event_writer: EventWriter<PlayerAttackEvent>,
+    balance: Res<GameBalance>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, dash)) = q_player.get_single() else {
+        return;
+    };
+    let position = player_transform.translation.truncate();
+
+    // Flee harder from closer swarmlings
+    let mut flee = Vec2::ZERO;
+    let mut nearest_threat: Option<(Vec2, f32)> = None;
+    for swarmling_transform in q_swarmling.iter() {
+        let offset = position - swarmling_transform.translation.truncate();
+        let distance = offset.length();
+        if distance < BOT_THREAT_RADIUS {
+            flee += offset.normalize_or_zero() * (1. - distance / BOT_THREAT_RADIUS);
+        }
+        let nearer = nearest_threat.is_none_or(|(_, nearest)| distance < nearest);
+        if distance < BOT_ENGAGE_RADIUS && nearer {
+            nearest_threat = Some((swarmling_transform.translation.truncate(), distance));
+        }
+    }
+    let nearest_hatchery = q_hatchery
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+
+    let direction = if flee != Vec2::ZERO {
+        flee.normalize()
+    } else if let Some(hatchery) = nearest_hatchery {
+        // Hold a firing distance from the hatchery
+        let distance = hatchery.distance(position);
+        if distance > BOT_HATCHERY_RANGE {
+            (hatchery - position).normalize_or_zero()
+        } else if distance < BOT_HATCHERY_RANGE * 0.7 {
+            (position - hatchery).normalize_or_zero()
+        } else {
+            Vec2::ZERO
+        }
+    } else {
+        Vec2::ZERO
+    };
+    movement_event_writer.send(PlayerMovementEvent::Move(direction));
+
+    // Cornered: something is about to hit us, or we tried to run and didn't get anywhere
+    let expected_step = balance.player.movement_speed * time.delta_secs();
+    let stuck = direction != Vec2::ZERO
+        && autopilot
+            .last_position
+            .is_some_and(|last| last.distance(position) < expected_step * 0.25);
+    let close_threat = nearest_threat.is_some_and(|(_, distance)| distance < BOT_DASH_RADIUS);
+    let dash_ready = dash.cooldown_fraction() <= 0. && !dash.is_dashing();
+    if dash_ready && (close_threat || (stuck && flee != Vec2::ZERO)) {
+        // Sidestep when stuck against something, straight away otherwise
+        let dash_direction = if stuck {
+            direction.perp()
+        } else {
+            direction
+        };
+        movement_event_writer.send(PlayerMovementEvent::Dash(dash_direction));
+    }
+    autopilot.last_position = Some(position);
+
+    autopilot.fire_cooldown -= time.delta_secs();
+    let target = nearest_threat.map(|(threat, _)| threat).or(nearest_hatchery);
+    if let Some(target) = target {
+        aim_event_writer.send(PlayerAimEvent(target - position));
+        if autopilot.fire_cooldown <= 0. {
+            attack_event_writer.send(PlayerAttackEvent::PrimaryFire);
+            autopilot.fire_cooldown = BOT_FIRE_INTERVAL;
+        }
+    }
+}
@@ -3,6 +3,10 @@ use crate::input::*;
 use crate::player::Player;
 use bevy::prelude::*;
 
+pub fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
+
 pub fn camera_follow(
     q_player: Query<&Transform, (With<Player>, Without<Camera>)>,
     mut q_camera: Query<&mut Transform, (With<Camera>, Without<Player>)>,
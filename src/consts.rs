@@ -3,24 +3,97 @@ use bevy::prelude::*;
 //Global constants
 pub const CAMERA_ZOOM_MIN: f32 = 0.5;
 pub const CAMERA_ZOOM_MAX: f32 = 5.0;
-pub const PROJECTILE_SPEED: f32 = 500.0;
-pub const SWARMLING_SPAWN_TIMER: f32 = 1.0;
-pub const SWARMLING_COLLISION_DAMAGE: f32 = 1.0;
-pub const SWARMLING_SPEED: f32 = 50.0;
-pub const SWARMLING_LAUNCH_SPEED: f32 = 1500.0;
-pub const PLAYER_MOVEMENT_SPEED: f32 = 100.;
-pub const PLAYER_DASH_SPEED: f32 = 750.;
-pub const PLAYER_DASH_DURATION: f32 = 0.3;
-pub const PLAYER_DASH_COOLDOWN: f32 = 1.0;
+pub const CAMERA_KEY_ZOOM_SPEED: f32 = 2.0;
+pub const CROSSHAIR_Z: f32 = 100.0;
+pub const CROSSHAIR_SCALE: f32 = 0.5;
+pub const CROSSHAIR_GAMEPAD_DISTANCE: f32 = 150.0;
+pub const CROSSHAIR_MIN_SPREAD_RADIUS: f32 = 4.0;
+pub const CROSSHAIR_RELOAD_RING_RADIUS: f32 = 24.0;
+pub const CROSSHAIR_HIT_MARKER_SIZE: f32 = 6.0;
+pub const CROSSHAIR_HIT_MARKER_DURATION: f32 = 0.12;
+pub const ARENA_TILES: i32 = 40;
+pub const ARENA_TILE_SIZE: f32 = 64.0;
+pub const ARENA_GROUND_Z: f32 = -2.0;
+pub const ARENA_GROUND_DARK: Color = Color::srgb(0.16, 0.14, 0.12);
+pub const ARENA_GROUND_LIGHT: Color = Color::srgb(0.26, 0.23, 0.18);
+pub const ARENA_ROCK_COLOR: Color = Color::srgb(0.35, 0.33, 0.32);
+pub const ARENA_ROCK_NOISE_FREQUENCY: f32 = 0.12;
+pub const ARENA_GROUND_NOISE_FREQUENCY: f32 = 0.05;
+pub const ARENA_ROCK_THRESHOLD: f32 = 0.45;
+/// Radii of the rocks inside the border, in tiles. Rocks share a mesh per size.
+pub const ARENA_ROCK_SIZES: [f32; 5] = [0.3, 0.35, 0.4, 0.45, 0.5];
+/// Radius of the border rocks, in tiles
+pub const ARENA_BORDER_ROCK_SIZE: f32 = 0.6;
+pub const ARENA_PLAYER_CLEARING_RADIUS: f32 = 300.0;
+pub const ARENA_HATCHERY_CLEARING_RADIUS: f32 = 150.0;
+pub const ARENA_HATCHERY_COUNT: usize = 3;
+pub const ARENA_HATCHERY_MIN_PLAYER_DISTANCE: f32 = 500.0;
+pub const ARENA_HATCHERY_MIN_SPACING: f32 = 400.0;
+pub const ARENA_HATCHERY_PLACEMENT_ATTEMPTS: usize = 200;
+pub const DECAL_Z: f32 = -1.0;
+pub const DECAL_TEXTURE_SIZE: f32 = 512.0;
+pub const MAX_DECALS: usize = 2000;
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const INPUT_BUFFER_WINDOW: f32 = 0.15;
+pub const BOT_THREAT_RADIUS: f32 = 250.0;
+pub const BOT_ENGAGE_RADIUS: f32 = 400.0;
+pub const BOT_DASH_RADIUS: f32 = 50.0;
+pub const BOT_HATCHERY_RANGE: f32 = 300.0;
+pub const BOT_FIRE_INTERVAL: f32 = 0.15;
+pub const GAMEPAD_STICK_DEADZONE: f32 = 0.2;
+pub const INPUT_MAP_PATH: &str = "config/input.ron";
 
 #[derive(Clone, Copy, Debug, Default, States, Hash, PartialEq, Eq, Reflect)]
 pub enum AppState {
     #[default]
     Start,
+    Paused,
+    /// Every hatchery has been destroyed
+    Victory,
+    /// The player died
+    GameOver,
+}
+
+//Custom Resources
+/// Seeded source of all gameplay randomness, so runs can be replayed exactly.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng {
+    pub seed: u64,
+    #[deref]
+    rng: fastrand::Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    /// Rebuilds a generator saved mid-run with [`GameRng::state`].
+    pub fn restore(seed: u64, state: u64) -> Self {
+        GameRng {
+            seed,
+            rng: fastrand::Rng::with_seed(state),
+        }
+    }
+
+    /// The generator's current internal state.
+    pub fn state(&self) -> u64 {
+        self.rng.get_seed()
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(fastrand::u64(..))
+    }
 }
 
 //Custom Components
 #[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
 pub struct Health(pub f32);
 
 impl Default for Health {
@@ -29,7 +102,8 @@ impl Default for Health {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Dying {
     pub timer: Timer,
 }
@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::f32::consts::TAU;
+
+use crate::camera::camera_follow;
+use crate::consts::*;
+use crate::input::*;
+use crate::player::Player;
+use crate::projectile::ProjectileHitEvent;
+use crate::weapon::Weapon;
+
+const CROSSHAIR_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.9);
+const HIT_MARKER_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+const SPREAD_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.25);
+const RELOAD_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+
+/// Replaces the OS cursor with the crosshair sprite while playing, with gizmos for
+/// weapon spread, reload progress and hit markers.
+pub struct CrosshairPlugin;
+
+impl Plugin for CrosshairPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HitMarker>()
+            .add_systems(Startup, spawn_crosshair)
+            .add_systems(
+                Update,
+                (
+                    crosshair_visibility.run_if(state_changed::<AppState>),
+                    hit_marker,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (crosshair_follow, crosshair_gizmos)
+                    .chain()
+                    .after(camera_follow)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct Crosshair;
+
+/// Counts down the hit-marker flash after a projectile lands.
+#[derive(Resource)]
+pub struct HitMarker {
+    timer: Timer,
+}
+
+impl Default for HitMarker {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(CROSSHAIR_HIT_MARKER_DURATION, TimerMode::Once);
+        timer.tick(timer.duration());
+        HitMarker { timer }
+    }
+}
+
+fn spawn_crosshair(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn((
+        Crosshair,
+        Name::new("Crosshair"),
+        Sprite {
+            image: assets.load("sprites/cursor/crosshair.png"),
+            color: CROSSHAIR_COLOR,
+            ..default()
+        },
+        Transform::from_xyz(0., 0., CROSSHAIR_Z),
+        Visibility::Hidden,
+    ));
+}
+
+/// Shows the crosshair and hides the OS cursor while playing, and the other way around in menus.
+fn crosshair_visibility(
+    state: Res<State<AppState>>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    mut q_crosshair: Query<&mut Visibility, With<Crosshair>>,
+) {
+    let playing = *state.get() == AppState::Start;
+    if let Ok(mut window) = q_window.get_single_mut() {
+        window.cursor_options.visible = !playing;
+    }
+    for mut visibility in q_crosshair.iter_mut() {
+        *visibility = if playing {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Places the crosshair at the cursor, or along the stick aim when using a gamepad.
+fn crosshair_follow(
+    mut q_crosshair: Query<&mut Transform, With<Crosshair>>,
+    q_player: Query<&Transform, (With<Player>, Without<Crosshair>)>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &Transform, &OrthographicProjection), Without<Crosshair>>,
+    action_state: Res<ActionState>,
+) {
+    let Ok(mut crosshair_transform) = q_crosshair.get_single_mut() else {
+        return;
+    };
+    let Ok((camera, camera_transform, projection)) = q_camera.get_single() else {
+        return;
+    };
+    // Keep the crosshair the same size on screen regardless of zoom
+    crosshair_transform.scale = Vec3::splat(CROSSHAIR_SCALE * projection.scale);
+
+    let position = if action_state.device == InputDevice::Gamepad {
+        q_player.get_single().ok().map(|player_transform| {
+            let forward = (player_transform.rotation * Vec3::X).truncate();
+            player_transform.translation.truncate() + forward * CROSSHAIR_GAMEPAD_DISTANCE
+        })
+    } else {
+        // The camera was just moved, so use its local transform instead of last frame's global one
+        q_window
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+            .and_then(|cursor_position| {
+                camera
+                    .viewport_to_world_2d(&GlobalTransform::from(*camera_transform), cursor_position)
+                    .ok()
+            })
+    };
+    if let Some(position) = position {
+        crosshair_transform.translation = position.extend(CROSSHAIR_Z);
+    }
+}
+
+fn crosshair_gizmos(
+    mut gizmos: Gizmos,
+    q_crosshair: Query<(&Transform, &Visibility), With<Crosshair>>,
+    q_player: Query<(&Transform, &Weapon), (With<Player>, Without<Crosshair>)>,
+    q_projection: Query<&OrthographicProjection>,
+    hit_marker: Res<HitMarker>,
+) {
+    let Ok((crosshair_transform, visibility)) = q_crosshair.get_single() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    let Ok((player_transform, weapon)) = q_player.get_single() else {
+        return;
+    };
+    let scale = q_projection.get_single().map_or(1.0, |projection| projection.scale);
+    let position = crosshair_transform.translation.truncate();
+
+    // Spread cone width at the crosshair's distance
+    let distance = position.distance(player_transform.translation.truncate());
+    let spread_radius = (distance * weapon.spread.tan()).max(CROSSHAIR_MIN_SPREAD_RADIUS * scale);
+    gizmos.circle_2d(Isometry2d::from_translation(position), spread_radius, SPREAD_COLOR);
+
+    if let Some(progress) = weapon.reload_progress() {
+        gizmos
+            .arc_2d(
+                Isometry2d::from_translation(position),
+                TAU * progress,
+                CROSSHAIR_RELOAD_RING_RADIUS * scale,
+                RELOAD_COLOR,
+            )
+            .resolution(48);
+    }
+
+    if !hit_marker.timer.finished() {
+        let size = CROSSHAIR_HIT_MARKER_SIZE * scale;
+        let gap = size * 0.5;
+        for diagonal in [Vec2::new(1., 1.), Vec2::new(1., -1.), Vec2::new(-1., 1.), Vec2::new(-1., -1.)] {
+            let direction = diagonal.normalize();
+            gizmos.line_2d(
+                position + direction * gap,
+                position + direction * (gap + size),
+                HIT_MARKER_COLOR,
+            );
+        }
+    }
+}
+
+fn hit_marker(
+    mut hit_marker: ResMut<HitMarker>,
+    mut projectile_hit_event_reader: EventReader<ProjectileHitEvent>,
+    mut q_crosshair: Query<&mut Sprite, With<Crosshair>>,
+    time: Res<Time>,
+) {
+    if projectile_hit_event_reader.read().next().is_some() {
+        hit_marker.timer.reset();
+    } else {
+        hit_marker.timer.tick(time.delta());
+    }
+    for mut sprite in q_crosshair.iter_mut() {
+        sprite.color = if hit_marker.timer.finished() {
+            CROSSHAIR_COLOR
+        } else {
+            HIT_MARKER_COLOR
+        };
+    }
+}
@@ -0,0 +1,171 @@
+use avian2d::prelude::*;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use super::console::*;
+use crate::arena::*;
+use crate::balance::GameBalance;
+use crate::consts::*;
+use crate::player::{Invulnerable, Player};
+use crate::projectile::Projectile;
+use crate::swarm::*;
+
+/// Swarmlings spawned by `spawn` land in a ring this far from the player.
+const SPAWN_RING: (f32, f32) = (150.0, 350.0);
+const SWARMLING_ARCHETYPES: &[&str] = &["basic"];
+
+/// Console commands for setting up gameplay scenarios.
+pub struct DevCommandsPlugin;
+
+impl Plugin for DevCommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command("spawn", "swarmling <n> [archetype]", spawn_command)
+            .add_console_command("god", "", god_command)
+            .add_console_command("kill_all", "", kill_all_command)
+            .add_console_command("set_health", "<health>", set_health_command)
+            .add_console_command("teleport", "<x> <y>", teleport_command)
+            .add_console_command("timescale", "[scale]", timescale_command)
+            .add_console_command("wave", "<n>", wave_command)
+            .add_console_command("seed", "<seed>", seed_command);
+    }
+}
+
+fn player_position(q_player: &Query<&Transform, With<Player>>) -> Result<Vec2, String> {
+    q_player
+        .get_single()
+        .map(|transform| transform.translation.truncate())
+        .map_err(|_| String::from("no player"))
+}
+
+fn spawn_command(
+    In(args): In<ConsoleArgs>,
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    balance: Res<GameBalance>,
+    q_player: Query<&Transform, With<Player>>,
+) -> ConsoleResult {
+    match args.first().map(String::as_str) {
+        Some("swarmling") => {}
+        Some(kind) => return Err(format!("can't spawn {:?}, expected swarmling", kind)),
+        None => return Err(String::from("missing <kind>")),
+    }
+    let count: usize = parse_arg(&args, 1, "n")?;
+    let archetype = args.get(2).map_or("basic", String::as_str);
+    if !SWARMLING_ARCHETYPES.contains(&archetype) {
+        return Err(format!(
+            "unknown archetype {:?}, expected one of {}",
+            archetype,
+            SWARMLING_ARCHETYPES.join(", ")
+        ));
+    }
+    let center = player_position(&q_player)?;
+    // Debug spawns use the global RNG so they don't shift the run's random sequence
+    for _ in 0..count {
+        let angle = fastrand::f32() * TAU;
+        let distance = SPAWN_RING.0 + fastrand::f32() * (SPAWN_RING.1 - SPAWN_RING.0);
+        let position = center + Vec2::from_angle(angle) * distance;
+        commands.spawn((
+            swarmling_bundle(&assets, fastrand::i32(0..100000), &balance.swarmling),
+            ExternalImpulse::default(),
+            Transform::from_translation(position.extend(0.)),
+        ));
+    }
+    Ok(format!("Spawned {} {} swarmlings", count, archetype))
+}
+
+fn god_command(
+    In(_): In<ConsoleArgs>,
+    mut commands: Commands,
+    q_player: Query<(Entity, Has<Invulnerable>), With<Player>>,
+) -> ConsoleResult {
+    let (player, invulnerable) = q_player.get_single().map_err(|_| "no player")?;
+    if invulnerable {
+        commands.entity(player).remove::<Invulnerable>();
+        Ok(String::from("God mode off"))
+    } else {
+        commands.entity(player).insert(Invulnerable);
+        Ok(String::from("God mode on"))
+    }
+}
+
+fn kill_all_command(
+    In(_): In<ConsoleArgs>,
+    mut q_swarmling: Query<&mut Health, With<Swarmling>>,
+) -> ConsoleResult {
+    // Zero health rather than despawning, so deaths score and leave effects as usual
+    let mut killed = 0;
+    for mut health in q_swarmling.iter_mut() {
+        health.0 = 0.;
+        killed += 1;
+    }
+    Ok(format!("Killed {} swarmlings", killed))
+}
+
+fn set_health_command(
+    In(args): In<ConsoleArgs>,
+    mut q_player: Query<&mut Health, With<Player>>,
+) -> ConsoleResult {
+    let value: f32 = parse_arg(&args, 0, "health")?;
+    let mut health = q_player.get_single_mut().map_err(|_| "no player")?;
+    health.0 = value;
+    Ok(format!("Player health set to {}", value))
+}
+
+fn teleport_command(
+    In(args): In<ConsoleArgs>,
+    mut q_player: Query<&mut Transform, With<Player>>,
+) -> ConsoleResult {
+    let x: f32 = parse_arg(&args, 0, "x")?;
+    let y: f32 = parse_arg(&args, 1, "y")?;
+    let mut transform = q_player.get_single_mut().map_err(|_| "no player")?;
+    transform.translation.x = x;
+    transform.translation.y = y;
+    Ok(format!("Teleported to ({}, {})", x, y))
+}
+
+fn timescale_command(In(args): In<ConsoleArgs>, mut time: ResMut<Time<Virtual>>) -> ConsoleResult {
+    if args.is_empty() {
+        return Ok(format!("Time scale is {}", time.relative_speed()));
+    }
+    let scale: f32 = parse_arg(&args, 0, "scale")?;
+    if scale.is_nan() || scale < 0. {
+        return Err(String::from("<scale> can't be negative"));
+    }
+    time.set_relative_speed(scale);
+    Ok(format!("Time scale set to {}", scale))
+}
+
+fn wave_command(In(args): In<ConsoleArgs>, mut wave: ResMut<Wave>) -> ConsoleResult {
+    let n: u32 = parse_arg(&args, 0, "n")?;
+    wave.0 = n.max(1);
+    Ok(format!("Wave set to {}", wave.0))
+}
+
+/// Reseeds the [`GameRng`] and, for generated arenas, rebuilds the arena from the new seed.
+fn seed_command(In(args): In<ConsoleArgs>, world: &mut World) -> ConsoleResult {
+    let seed: u64 = parse_arg(&args, 0, "seed")?;
+    world.insert_resource(GameRng::new(seed));
+    if !matches!(world.resource::<ArenaSource>(), ArenaSource::Generated) {
+        return Ok(format!("Reseeded to {}", seed));
+    }
+
+    let stale: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(
+            With<ArenaGround>,
+            With<Rock>,
+            With<Hatchery>,
+            With<Swarmling>,
+            With<Projectile>,
+        )>>()
+        .iter(world)
+        .collect();
+    for entity in stale {
+        world.entity_mut(entity).despawn_recursive();
+    }
+    *world.resource_mut::<Wave>() = Wave::default();
+    world
+        .run_system_once(spawn_arena)
+        .map_err(|err| err.to_string())?;
+    Ok(format!("Reseeded to {} and regenerated the arena", seed))
+}
@@ -0,0 +1,235 @@
+use bevy::ecs::system::SystemId;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::input::update_action_state;
+
+/// Lines of output kept in the console.
+const CONSOLE_LOG_SIZE: usize = 200;
+const CONSOLE_HEIGHT: f32 = 260.0;
+
+/// Arguments after the command name, split on whitespace.
+pub type ConsoleArgs = Vec<String>;
+/// A line to print on success, or an error message.
+pub type ConsoleResult = Result<String, String>;
+
+/// A drop-down command console, opened with the backquote key.
+///
+/// Commands are systems taking [`ConsoleArgs`] and returning a [`ConsoleResult`], added
+/// by any plugin with [`ConsoleCommandExt::add_console_command`].
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>()
+            .init_resource::<Console>()
+            .add_console_command("help", "", help_command)
+            .add_systems(
+                PreUpdate,
+                capture_console_input
+                    .after(InputSystem)
+                    .before(update_action_state),
+            )
+            .add_systems(Update, (console_ui, run_console_commands).chain());
+    }
+}
+
+pub struct ConsoleCommand {
+    /// Arguments, shown by `help`
+    pub usage: String,
+    system: SystemId<In<ConsoleArgs>, ConsoleResult>,
+}
+
+/// Every registered command by name.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(BTreeMap<String, ConsoleCommand>);
+
+pub trait ConsoleCommandExt {
+    /// Registers `system` to run when a console line starts with `name`.
+    fn add_console_command<M>(
+        &mut self,
+        name: &str,
+        usage: &str,
+        system: impl IntoSystem<In<ConsoleArgs>, ConsoleResult, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl ConsoleCommandExt for App {
+    fn add_console_command<M>(
+        &mut self,
+        name: &str,
+        usage: &str,
+        system: impl IntoSystem<In<ConsoleArgs>, ConsoleResult, M> + 'static,
+    ) -> &mut Self {
+        let system = self.register_system(system);
+        self.init_resource::<ConsoleCommands>()
+            .world_mut()
+            .resource_mut::<ConsoleCommands>()
+            .0
+            .insert(
+                String::from(name),
+                ConsoleCommand {
+                    usage: String::from(usage),
+                    system,
+                },
+            );
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+    history: Vec<String>,
+    /// Position while browsing the history with the arrow keys
+    history_index: Option<usize>,
+    focus_input: bool,
+    pending: Vec<String>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > CONSOLE_LOG_SIZE {
+            let excess = self.log.len() - CONSOLE_LOG_SIZE;
+            self.log.drain(..excess);
+        }
+    }
+
+    /// Queues a command line to run this frame, as if typed.
+    pub fn submit(&mut self, line: impl Into<String>) {
+        self.pending.push(line.into());
+    }
+}
+
+/// Toggles the console, and keeps typing from reaching the game while it is open.
+fn capture_console_input(
+    mut console: ResMut<Console>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_buttons: ResMut<ButtonInput<MouseButton>>,
+) {
+    if keys.just_pressed(KeyCode::Backquote) {
+        console.open = !console.open;
+        console.focus_input = console.open;
+    }
+    if console.open {
+        keys.reset_all();
+        mouse_buttons.reset_all();
+    }
+}
+
+fn console_ui(mut contexts: EguiContexts, mut console: ResMut<Console>) {
+    if !console.open {
+        return;
+    }
+    let console = console.as_mut();
+    egui::TopBottomPanel::top("console")
+        .exact_height(CONSOLE_HEIGHT)
+        .show(contexts.ctx_mut(), |ui| {
+            let input_height = ui.spacing().interact_size.y + 8.;
+            egui::ScrollArea::vertical()
+                .max_height(CONSOLE_HEIGHT - input_height)
+                .auto_shrink(false)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in console.log.iter() {
+                        ui.monospace(line);
+                    }
+                });
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut console.input)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(f32::INFINITY),
+            );
+            // The key that opened the console shouldn't end up in the input
+            console.input.retain(|c| c != '`');
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let line = console.input.trim().to_string();
+                console.input.clear();
+                console.history_index = None;
+                if !line.is_empty() {
+                    console.history.push(line.clone());
+                    console.submit(line);
+                }
+                console.focus_input = true;
+            } else if response.has_focus() {
+                browse_history(ui, console);
+            }
+            if console.focus_input {
+                response.request_focus();
+                console.focus_input = false;
+            }
+        });
+}
+
+fn browse_history(ui: &egui::Ui, console: &mut Console) {
+    if console.history.is_empty() {
+        return;
+    }
+    let last = console.history.len() - 1;
+    if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
+        let index = console.history_index.map_or(last, |index| index.saturating_sub(1));
+        console.history_index = Some(index);
+        console.input = console.history[index].clone();
+    } else if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
+        match console.history_index {
+            Some(index) if index < last => {
+                console.history_index = Some(index + 1);
+                console.input = console.history[index + 1].clone();
+            }
+            Some(_) => {
+                console.history_index = None;
+                console.input.clear();
+            }
+            None => {}
+        }
+    }
+}
+
+fn run_console_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in pending {
+        world.resource_mut::<Console>().print(format!("> {}", line));
+        let mut words = line.split_whitespace().map(String::from);
+        let Some(name) = words.next() else {
+            continue;
+        };
+        let system = world
+            .resource::<ConsoleCommands>()
+            .0
+            .get(&name)
+            .map(|command| command.system);
+        let output = match system {
+            Some(system) => world
+                .run_system_with_input(system, words.collect())
+                .unwrap_or_else(|err| Err(err.to_string())),
+            None => Err(format!("Unknown command {:?}, try help", name)),
+        };
+        let mut console = world.resource_mut::<Console>();
+        match output {
+            Ok(message) if message.is_empty() => {}
+            Ok(message) => console.print(message),
+            Err(message) => console.print(format!("error: {}", message)),
+        }
+    }
+}
+
+fn help_command(In(_): In<ConsoleArgs>, commands: Res<ConsoleCommands>) -> ConsoleResult {
+    Ok(commands
+        .0
+        .iter()
+        .map(|(name, command)| format!("{} {}", name, command.usage).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Parses the argument at `index`, naming it in the error.
+pub fn parse_arg<T: FromStr>(args: &[String], index: usize, name: &str) -> Result<T, String> {
+    let arg = args.get(index).ok_or(format!("missing <{}>", name))?;
+    arg.parse().map_err(|_| format!("<{}> can't be {:?}", name, arg))
+}
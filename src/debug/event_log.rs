@@ -0,0 +1,476 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::console::*;
+use crate::arena::Rock;
+use crate::consts::*;
+use crate::player::{Player, PlayerDamageEvent, PlayerDeathEvent};
+use crate::projectile::{Projectile, ProjectileFiredEvent, ProjectileHitEvent};
+use crate::swarm::{Hatchery, HatcheryDestroyed, Swarmling, SwarmlingDied};
+
+/// Entries kept before the oldest are dropped.
+pub const EVENT_LOG_CAPACITY: usize = 5000;
+/// Where exported logs are written.
+pub const EVENT_LOG_EXPORT_DIR: &str = "logs";
+
+/// Records gameplay events into a ring buffer and shows them in a filterable window.
+/// F6 or the `events` command shows it.
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventLog>()
+            .add_console_command("events", "[pause|resume|clear|export]", events_command)
+            .add_systems(
+                Update,
+                (
+                    toggle_event_log,
+                    // These keep reading while paused, so nothing from the pause is still
+                    // buffered when the log resumes
+                    (
+                        log_collisions,
+                        log_damage,
+                        log_deaths,
+                        log_state_transitions,
+                        log_spawns,
+                    ),
+                    event_log_panel.run_if(|log: Res<EventLog>| log.open),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogCategory {
+    Collision,
+    Damage,
+    Death,
+    State,
+    Spawn,
+}
+
+impl LogCategory {
+    const ALL: [LogCategory; 5] = [
+        LogCategory::Collision,
+        LogCategory::Damage,
+        LogCategory::Death,
+        LogCategory::State,
+        LogCategory::Spawn,
+    ];
+}
+
+/// What a collider belongs to, for filtering collisions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    Player,
+    Swarmling,
+    Projectile,
+    Hatchery,
+    Rock,
+    Other,
+}
+
+impl BodyKind {
+    const ALL: [BodyKind; 6] = [
+        BodyKind::Player,
+        BodyKind::Swarmling,
+        BodyKind::Projectile,
+        BodyKind::Hatchery,
+        BodyKind::Rock,
+        BodyKind::Other,
+    ];
+}
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Game time in seconds
+    pub time: f32,
+    pub category: LogCategory,
+    /// Both sides of a collision
+    pub bodies: Option<(BodyKind, BodyKind)>,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{:>8.3}] {:?}: {}",
+            self.time, self.category, self.message
+        )
+    }
+}
+
+#[derive(Resource)]
+pub struct EventLog {
+    pub open: bool,
+    /// Drops new entries, so the log holds still while reading it
+    pub paused: bool,
+    entries: VecDeque<LogEntry>,
+    categories: Vec<LogCategory>,
+    bodies: Vec<BodyKind>,
+    search: String,
+    status: String,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog {
+            open: false,
+            paused: false,
+            entries: VecDeque::with_capacity(EVENT_LOG_CAPACITY),
+            categories: LogCategory::ALL.to_vec(),
+            bodies: BodyKind::ALL.to_vec(),
+            search: String::new(),
+            status: String::new(),
+        }
+    }
+}
+
+impl EventLog {
+    pub fn push(&mut self, time: f32, category: LogCategory, message: String) {
+        self.push_entry(LogEntry {
+            time,
+            category,
+            bodies: None,
+            message,
+        });
+    }
+
+    fn push_entry(&mut self, entry: LogEntry) {
+        if self.paused {
+            return;
+        }
+        if self.entries.len() == EVENT_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn shows(&self, entry: &LogEntry) -> bool {
+        if !self.categories.contains(&entry.category) {
+            return false;
+        }
+        if let Some((a, b)) = entry.bodies {
+            // A collision shows when either side is a kind we're watching
+            if !self.bodies.contains(&a) && !self.bodies.contains(&b) {
+                return false;
+            }
+        }
+        self.search.is_empty() || entry.message.contains(self.search.as_str())
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter().filter(|entry| self.shows(entry))
+    }
+
+    /// Writes the entries passing the current filters to a timestamped file.
+    pub fn export(&self) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = PathBuf::from(EVENT_LOG_EXPORT_DIR).join(format!("events-{}.log", timestamp));
+        fs::create_dir_all(EVENT_LOG_EXPORT_DIR).map_err(|e| e.to_string())?;
+        let contents: String = self.visible().map(|entry| format!("{}\n", entry)).collect();
+        fs::write(&path, contents).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
+
+type BodyQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Name>,
+        Has<Player>,
+        Has<Swarmling>,
+        Has<Projectile>,
+        Has<Hatchery>,
+        Has<Rock>,
+    ),
+>;
+
+fn body_kind(q_body: &BodyQuery, entity: Entity) -> (BodyKind, String) {
+    let Ok((name, player, swarmling, projectile, hatchery, rock)) = q_body.get(entity) else {
+        return (BodyKind::Other, format!("{}", entity));
+    };
+    let kind = if player {
+        BodyKind::Player
+    } else if swarmling {
+        BodyKind::Swarmling
+    } else if projectile {
+        BodyKind::Projectile
+    } else if hatchery {
+        BodyKind::Hatchery
+    } else if rock {
+        BodyKind::Rock
+    } else {
+        BodyKind::Other
+    };
+    let label = match (kind, name) {
+        (BodyKind::Other, Some(name)) => format!("{} {}", name, entity),
+        _ => format!("{:?} {}", kind, entity),
+    };
+    (kind, label)
+}
+
+fn position(position: Vec2) -> String {
+    format!("({:.0}, {:.0})", position.x, position.y)
+}
+
+fn toggle_event_log(keys: Res<ButtonInput<KeyCode>>, mut log: ResMut<EventLog>) {
+    if keys.just_pressed(KeyCode::F6) {
+        log.open = !log.open;
+    }
+}
+
+fn events_command(In(args): In<ConsoleArgs>, mut log: ResMut<EventLog>) -> ConsoleResult {
+    match args.first().map(String::as_str) {
+        None => {
+            log.open = !log.open;
+            Ok(String::new())
+        }
+        Some("pause") => {
+            log.paused = true;
+            Ok(String::from("Event log paused"))
+        }
+        Some("resume") => {
+            log.paused = false;
+            Ok(String::from("Event log recording"))
+        }
+        Some("clear") => {
+            log.clear();
+            Ok(String::from("Event log cleared"))
+        }
+        Some("export") => log
+            .export()
+            .map(|path| format!("Exported event log to {}", path.display())),
+        Some(other) => Err(format!("unknown events action {:?}", other)),
+    }
+}
+
+/// Only the first contact of each pair, rather than every contact every frame.
+fn log_collisions(
+    mut log: ResMut<EventLog>,
+    mut collision_started_reader: EventReader<CollisionStarted>,
+    q_body: BodyQuery,
+    time: Res<Time>,
+) {
+    for CollisionStarted(a, b) in collision_started_reader.read() {
+        let (kind_a, label_a) = body_kind(&q_body, *a);
+        let (kind_b, label_b) = body_kind(&q_body, *b);
+        log.push_entry(LogEntry {
+            time: time.elapsed_secs(),
+            category: LogCategory::Collision,
+            bodies: Some((kind_a, kind_b)),
+            message: format!("{} touched {}", label_a, label_b),
+        });
+    }
+}
+
+fn log_damage(
+    mut log: ResMut<EventLog>,
+    mut player_damage_event_reader: EventReader<PlayerDamageEvent>,
+    mut projectile_hit_event_reader: EventReader<ProjectileHitEvent>,
+    q_body: BodyQuery,
+    q_health: Query<&Health>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+    for event in player_damage_event_reader.read() {
+        let PlayerDamageEvent::Collision(damage) = event;
+        log.push(
+            now,
+            LogCategory::Damage,
+            format!("Player took {} contact damage", damage),
+        );
+    }
+    for hit in projectile_hit_event_reader.read() {
+        let (_, label) = body_kind(&q_body, hit.target);
+        let health = q_health
+            .get(hit.target)
+            .map_or(String::from("gone"), |health| format!("{:.1}", health.0));
+        log.push(
+            now,
+            LogCategory::Damage,
+            format!(
+                "Projectile hit {} at {}, health {}",
+                label,
+                position(hit.position),
+                health
+            ),
+        );
+    }
+}
+
+fn log_deaths(
+    mut log: ResMut<EventLog>,
+    mut swarmling_died_event_reader: EventReader<SwarmlingDied>,
+    mut hatchery_destroyed_event_reader: EventReader<HatcheryDestroyed>,
+    mut player_death_event_reader: EventReader<PlayerDeathEvent>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+    for died in swarmling_died_event_reader.read() {
+        log.push(
+            now,
+            LogCategory::Death,
+            format!(
+                "Swarmling {} died at {}",
+                died.entity,
+                position(died.position)
+            ),
+        );
+    }
+    for destroyed in hatchery_destroyed_event_reader.read() {
+        log.push(
+            now,
+            LogCategory::Death,
+            format!(
+                "Hatchery {} destroyed at {}, {} left",
+                destroyed.entity,
+                position(destroyed.position),
+                destroyed.remaining
+            ),
+        );
+    }
+    for _ in player_death_event_reader.read() {
+        log.push(now, LogCategory::Death, String::from("Player died"));
+    }
+}
+
+fn log_state_transitions(
+    mut log: ResMut<EventLog>,
+    mut state_transition_reader: EventReader<StateTransitionEvent<AppState>>,
+    time: Res<Time>,
+) {
+    for transition in state_transition_reader.read() {
+        log.push(
+            time.elapsed_secs(),
+            LogCategory::State,
+            format!("{:?} -> {:?}", transition.exited, transition.entered),
+        );
+    }
+}
+
+fn log_spawns(
+    mut log: ResMut<EventLog>,
+    q_swarmling: Query<(Entity, &Transform), Added<Swarmling>>,
+    q_hatchery: Query<(Entity, &Transform), Added<Hatchery>>,
+    mut projectile_fired_event_reader: EventReader<ProjectileFiredEvent>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+    for (entity, transform) in q_swarmling.iter() {
+        log.push(
+            now,
+            LogCategory::Spawn,
+            format!(
+                "Swarmling {} at {}",
+                entity,
+                position(transform.translation.truncate())
+            ),
+        );
+    }
+    for (entity, transform) in q_hatchery.iter() {
+        log.push(
+            now,
+            LogCategory::Spawn,
+            format!(
+                "Hatchery {} at {}",
+                entity,
+                position(transform.translation.truncate())
+            ),
+        );
+    }
+    for fired in projectile_fired_event_reader.read() {
+        log.push(
+            now,
+            LogCategory::Spawn,
+            format!("Projectile fired from {}", position(fired.position)),
+        );
+    }
+}
+
+fn toggle_filter<T: PartialEq + Copy>(
+    ui: &mut egui::Ui,
+    filter: &mut Vec<T>,
+    value: T,
+    label: String,
+) {
+    let mut enabled = filter.contains(&value);
+    if ui.checkbox(&mut enabled, label).changed() {
+        if enabled {
+            filter.push(value);
+        } else {
+            filter.retain(|other| *other != value);
+        }
+    }
+}
+
+fn event_log_panel(mut contexts: EguiContexts, mut log: ResMut<EventLog>) {
+    let log = log.as_mut();
+    egui::Window::new("Event log")
+        .default_width(560.)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let pause_label = if log.paused { "Resume" } else { "Pause" };
+                if ui.button(pause_label).clicked() {
+                    log.paused = !log.paused;
+                }
+                if ui.button("Clear").clicked() {
+                    log.clear();
+                }
+                if ui.button("Export").clicked() {
+                    log.status = match log.export() {
+                        Ok(path) => format!("Exported to {}", path.display()),
+                        Err(err) => format!("Export failed: {}", err),
+                    };
+                }
+                ui.label(&log.status);
+            });
+            ui.horizontal_wrapped(|ui| {
+                for category in LogCategory::ALL {
+                    toggle_filter(ui, &mut log.categories, category, format!("{:?}", category));
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Collisions with");
+                for kind in BodyKind::ALL {
+                    toggle_filter(ui, &mut log.bodies, kind, format!("{:?}", kind));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Search");
+                ui.text_edit_singleline(&mut log.search);
+            });
+            ui.separator();
+
+            let visible: Vec<&LogEntry> = log.visible().collect();
+            ui.label(format!(
+                "{} of {} entries",
+                visible.len(),
+                log.entries.len()
+            ));
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::vertical()
+                .max_height(360.)
+                .auto_shrink(false)
+                .stick_to_bottom(true)
+                .show_rows(ui, row_height, visible.len(), |ui, rows| {
+                    for entry in &visible[rows] {
+                        ui.monospace(entry.to_string());
+                    }
+                });
+        });
+}
@@ -7,6 +7,18 @@ use bevy::{
 };
 use bevy_inspector_egui::quick::{StateInspectorPlugin, WorldInspectorPlugin};
 
+mod commands;
+mod console;
+mod event_log;
+mod swarm_ai;
+mod tuning;
+
+pub use commands::*;
+pub use console::*;
+pub use event_log::*;
+pub use swarm_ai::*;
+pub use tuning::*;
+
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
@@ -25,15 +37,12 @@ impl Plugin for DebugPlugin {
             },
         });
         app.add_plugins(PhysicsDebugPlugin::default());
-        // app.add_systems(PostProcessCollisions, print_collisions);
-    }
-}
-
-fn print_collisions(mut collision_event_reader: EventReader<Collision>) {
-    for Collision(contacts) in collision_event_reader.read() {
-        println!(
-            "Entities {} and {} are colliding",
-            contacts.entity1, contacts.entity2,
-        );
+        app.add_plugins((
+            ConsolePlugin,
+            DevCommandsPlugin,
+            EventLogPlugin,
+            SwarmDebugPlugin,
+            TuningPlugin,
+        ));
     }
 }
@@ -0,0 +1,178 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::balance::GameBalance;
+use crate::player::Player;
+use crate::swarm::{BehaviorState, Swarmling};
+
+const BEHAVIOR_STATES: [BehaviorState; 3] = [
+    BehaviorState::Wander,
+    BehaviorState::Flee,
+    BehaviorState::Attack,
+];
+const STATE_MARKER_RADIUS: f32 = 7.0;
+const CENTROID_MARKER_SIZE: f32 = 12.0;
+/// Seconds of velocity shown by a velocity arrow.
+const VELOCITY_ARROW_SCALE: f32 = 0.5;
+const VELOCITY_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+const PERCEPTION_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+const NEIGHBOR_COLOR: Color = Color::srgba(0.6, 0.4, 1.0, 0.3);
+const CENTROID_COLOR: Color = Color::srgb(1.0, 0.3, 1.0);
+
+/// Gizmo overlays and a stats panel for the swarm AI. F3 shows or hides the panel.
+pub struct SwarmDebugPlugin;
+
+impl Plugin for SwarmDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SwarmDebugOverlay>().add_systems(
+            Update,
+            (
+                toggle_swarm_debug_panel,
+                swarm_debug_panel.run_if(|overlay: Res<SwarmDebugOverlay>| overlay.panel),
+                draw_swarm_debug,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Which swarm AI overlays are drawn.
+#[derive(Resource, Debug)]
+pub struct SwarmDebugOverlay {
+    pub panel: bool,
+    pub states: bool,
+    pub velocities: bool,
+    pub perception: bool,
+    pub neighbors: bool,
+    pub targets: bool,
+    pub centroid: bool,
+}
+
+impl Default for SwarmDebugOverlay {
+    fn default() -> Self {
+        SwarmDebugOverlay {
+            panel: false,
+            states: true,
+            velocities: false,
+            perception: false,
+            neighbors: false,
+            targets: true,
+            centroid: true,
+        }
+    }
+}
+
+impl SwarmDebugOverlay {
+    fn any(&self) -> bool {
+        self.states
+            || self.velocities
+            || self.perception
+            || self.neighbors
+            || self.targets
+            || self.centroid
+    }
+}
+
+fn state_color(state: BehaviorState) -> Color {
+    match state {
+        BehaviorState::Wander => Color::srgb(0.3, 0.9, 0.3),
+        BehaviorState::Flee => Color::srgb(1.0, 0.85, 0.2),
+        BehaviorState::Attack => Color::srgb(1.0, 0.25, 0.2),
+    }
+}
+
+fn toggle_swarm_debug_panel(keys: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<SwarmDebugOverlay>) {
+    if keys.just_pressed(KeyCode::F3) {
+        overlay.panel = !overlay.panel;
+    }
+}
+
+fn swarm_debug_panel(
+    mut contexts: EguiContexts,
+    mut overlay: ResMut<SwarmDebugOverlay>,
+    q_swarmling: Query<(&Swarmling, &LinearVelocity)>,
+) {
+    let mut counts = [0usize; BEHAVIOR_STATES.len()];
+    let mut total_speed = 0.;
+    for (swarmling, velocity) in q_swarmling.iter() {
+        if let Some(index) = BEHAVIOR_STATES.iter().position(|state| *state == swarmling.state()) {
+            counts[index] += 1;
+        }
+        total_speed += velocity.length();
+    }
+    let total = q_swarmling.iter().count();
+
+    egui::Window::new("Swarm AI").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!("Swarmlings: {}", total));
+        for (state, count) in BEHAVIOR_STATES.iter().zip(counts) {
+            let [r, g, b, _] = state_color(*state).to_srgba().to_u8_array();
+            ui.colored_label(
+                egui::Color32::from_rgb(r, g, b),
+                format!("{:?}: {}", state, count),
+            );
+        }
+        if total > 0 {
+            ui.label(format!("Mean speed: {:.1}", total_speed / total as f32));
+        }
+        ui.separator();
+        ui.checkbox(&mut overlay.states, "Behavior states");
+        ui.checkbox(&mut overlay.velocities, "Velocities");
+        ui.checkbox(&mut overlay.perception, "Perception radius");
+        ui.checkbox(&mut overlay.neighbors, "Neighbor radius");
+        ui.checkbox(&mut overlay.targets, "Targets");
+        ui.checkbox(&mut overlay.centroid, "Centroid");
+    });
+}
+
+fn draw_swarm_debug(
+    mut gizmos: Gizmos,
+    overlay: Res<SwarmDebugOverlay>,
+    balance: Res<GameBalance>,
+    q_swarmling: Query<(&Swarmling, &Transform, &LinearVelocity)>,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    if !overlay.any() {
+        return;
+    }
+    let player = q_player
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+
+    let mut sum = Vec2::ZERO;
+    for (swarmling, transform, velocity) in q_swarmling.iter() {
+        let position = transform.translation.truncate();
+        let state = swarmling.state();
+        sum += position;
+        if overlay.states {
+            gizmos.circle_2d(position, STATE_MARKER_RADIUS, state_color(state));
+        }
+        if overlay.velocities && velocity.0 != Vec2::ZERO {
+            gizmos.arrow_2d(
+                position,
+                position + velocity.0 * VELOCITY_ARROW_SCALE,
+                VELOCITY_COLOR,
+            );
+        }
+        if overlay.perception {
+            gizmos.circle_2d(position, balance.swarmling.perception_radius, PERCEPTION_COLOR);
+        }
+        if overlay.neighbors {
+            gizmos.circle_2d(position, balance.swarmling.neighbor_radius, NEIGHBOR_COLOR);
+        }
+        if overlay.targets {
+            // Fleeing and attacking swarmlings are both reacting to the player
+            if let (Some(player), BehaviorState::Flee | BehaviorState::Attack) = (player, state) {
+                gizmos.line_2d(position, player, state_color(state));
+            }
+        }
+    }
+
+    let count = q_swarmling.iter().count();
+    if overlay.centroid && count > 0 {
+        let centroid = sum / count as f32;
+        gizmos.cross_2d(centroid, CENTROID_MARKER_SIZE, CENTROID_COLOR);
+        gizmos.circle_2d(centroid, CENTROID_MARKER_SIZE, CENTROID_COLOR);
+    }
+}
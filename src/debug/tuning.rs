@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use super::console::*;
+use crate::balance::GameBalance;
+
+/// Where tuning presets are saved, one `<name>.balance.ron` file each.
+pub const TUNING_PRESETS_DIR: &str = "config/presets";
+const PRESET_EXTENSION: &str = ".balance.ron";
+
+/// A window of grouped sliders over the [`GameBalance`], with presets on disk and two
+/// slots to flip between while playing. F4 or the `tuning` command shows it, F5 flips
+/// between the A and B slots even while it is hidden.
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TuningPanel>()
+            .add_console_command("tuning", "", tuning_command)
+            .add_systems(
+                Update,
+                (
+                    tuning_keys,
+                    tuning_panel.run_if(|panel: Res<TuningPanel>| panel.open),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Slot {
+    A,
+    B,
+}
+
+#[derive(Resource)]
+pub struct TuningPanel {
+    pub open: bool,
+    active: Slot,
+    a: GameBalance,
+    b: GameBalance,
+    /// Whether the slots have been filled from the running balance yet
+    filled: bool,
+    preset_name: String,
+    presets: Vec<String>,
+    status: String,
+}
+
+impl Default for TuningPanel {
+    fn default() -> Self {
+        TuningPanel {
+            open: false,
+            active: Slot::A,
+            a: GameBalance::default(),
+            b: GameBalance::default(),
+            filled: false,
+            preset_name: String::from("preset"),
+            presets: Vec::new(),
+            status: String::new(),
+        }
+    }
+}
+
+impl TuningPanel {
+    fn slot_mut(&mut self, slot: Slot) -> &mut GameBalance {
+        match slot {
+            Slot::A => &mut self.a,
+            Slot::B => &mut self.b,
+        }
+    }
+
+    fn active_mut(&mut self) -> &mut GameBalance {
+        self.slot_mut(self.active)
+    }
+
+    fn fill_slots(&mut self, balance: &GameBalance) {
+        if !self.filled {
+            self.a = balance.clone();
+            self.b = balance.clone();
+            self.filled = true;
+        }
+    }
+
+    /// Makes the other slot active, returning its balance.
+    fn flip(&mut self) -> GameBalance {
+        self.active = match self.active {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        };
+        self.active_mut().clone()
+    }
+
+    fn refresh_presets(&mut self) {
+        self.presets = list_presets(TUNING_PRESETS_DIR);
+    }
+
+    fn toggle(&mut self) {
+        self.open = !self.open;
+        if self.open {
+            self.refresh_presets();
+        }
+    }
+}
+
+fn preset_path(name: &str) -> PathBuf {
+    Path::new(TUNING_PRESETS_DIR).join(format!("{}{}", name, PRESET_EXTENSION))
+}
+
+fn list_presets(dir: impl AsRef<Path>) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut presets: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            file_name.strip_suffix(PRESET_EXTENSION).map(String::from)
+        })
+        .collect();
+    presets.sort();
+    presets
+}
+
+fn tuning_command(In(_): In<ConsoleArgs>, mut panel: ResMut<TuningPanel>) -> ConsoleResult {
+    panel.toggle();
+    Ok(String::new())
+}
+
+fn tuning_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut panel: ResMut<TuningPanel>,
+    mut balance: ResMut<GameBalance>,
+) {
+    if keys.just_pressed(KeyCode::F4) {
+        panel.toggle();
+    }
+    if keys.just_pressed(KeyCode::F5) {
+        panel.fill_slots(&balance);
+        let flipped = panel.flip();
+        balance.set_if_neq(flipped);
+        info!("Tuning slot {:?} active", panel.active);
+    }
+}
+
+fn slider(ui: &mut egui::Ui, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+    ui.add(egui::Slider::new(value, range).text(label)).changed()
+}
+
+fn slider_u32(ui: &mut egui::Ui, label: &str, value: &mut u32, range: RangeInclusive<u32>) -> bool {
+    ui.add(egui::Slider::new(value, range).text(label)).changed()
+}
+
+/// Draws the sliders for `balance`, returning whether anything was changed.
+fn balance_sliders(ui: &mut egui::Ui, balance: &mut GameBalance) -> bool {
+    let mut changed = false;
+    egui::CollapsingHeader::new("Swarm")
+        .default_open(true)
+        .show(ui, |ui| {
+            let swarmling = &mut balance.swarmling;
+            changed |= slider(ui, "Wander speed", &mut swarmling.speed, 0.0..=300.0);
+            changed |= slider(ui, "Launch impulse", &mut swarmling.launch_speed, 0.0..=5000.0);
+            changed |= slider(ui, "Perception radius", &mut swarmling.perception_radius, 0.0..=600.0);
+            changed |= slider(ui, "Neighbor radius", &mut swarmling.neighbor_radius, 0.0..=200.0);
+            changed |= slider(ui, "Health", &mut swarmling.health, 0.1..=20.0);
+            changed |= slider(ui, "Contact damage", &mut swarmling.collision_damage, 0.0..=5.0);
+        });
+    egui::CollapsingHeader::new("Hatcheries")
+        .default_open(true)
+        .show(ui, |ui| {
+            let hatchery = &mut balance.hatchery;
+            changed |= slider(
+                ui,
+                "Spawn interval (new hatcheries)",
+                &mut hatchery.spawn_interval,
+                0.05..=10.0,
+            );
+            changed |= slider(ui, "Panic spawn factor", &mut hatchery.panic_spawn_factor, 0.05..=1.0);
+            changed |= slider(ui, "Health (new hatcheries)", &mut hatchery.health, 1.0..=100.0);
+        });
+    egui::CollapsingHeader::new("Player")
+        .default_open(true)
+        .show(ui, |ui| {
+            let player = &mut balance.player;
+            changed |= slider(ui, "Move speed", &mut player.movement_speed, 0.0..=400.0);
+            changed |= slider(ui, "Dash speed", &mut player.dash_speed, 0.0..=2000.0);
+            changed |= slider(ui, "Dash duration", &mut player.dash_duration, 0.05..=1.0);
+            changed |= slider(ui, "Dash cooldown", &mut player.dash_cooldown, 0.0..=5.0);
+        });
+    egui::CollapsingHeader::new("Weapon and projectiles")
+        .default_open(true)
+        .show(ui, |ui| {
+            let weapon = &mut balance.weapon;
+            changed |= slider(ui, "Spread", &mut weapon.spread, 0.0..=0.5);
+            changed |= slider_u32(ui, "Magazine size", &mut weapon.magazine_size, 1..=100);
+            changed |= slider(ui, "Reload time", &mut weapon.reload_time, 0.0..=5.0);
+            let projectile = &mut balance.projectile;
+            changed |= slider(ui, "Projectile speed", &mut projectile.speed, 50.0..=2000.0);
+            changed |= slider(ui, "Projectile range", &mut projectile.range, 50.0..=3000.0);
+            changed |= slider(ui, "Projectile damage", &mut projectile.damage, 0.1..=10.0);
+        });
+    changed
+}
+
+fn tuning_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<TuningPanel>,
+    mut balance: ResMut<GameBalance>,
+) {
+    let panel = panel.as_mut();
+    panel.fill_slots(&balance);
+    // Changes from elsewhere, like a hot-reloaded balance file, land in the active slot
+    if balance.is_changed() {
+        *panel.active_mut() = balance.clone();
+    }
+
+    let mut apply = false;
+    egui::Window::new("Tuning").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for slot in [Slot::A, Slot::B] {
+                if ui
+                    .selectable_label(panel.active == slot, format!("{:?}", slot))
+                    .clicked()
+                    && panel.active != slot
+                {
+                    panel.flip();
+                    apply = true;
+                }
+            }
+            let other = if panel.active == Slot::A { Slot::B } else { Slot::A };
+            if ui.button(format!("Copy to {:?}", other)).clicked() {
+                let active = panel.active_mut().clone();
+                *panel.slot_mut(other) = active;
+            }
+            if ui.button("Defaults").clicked() {
+                *panel.active_mut() = GameBalance::default();
+                apply = true;
+            }
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical().max_height(420.).show(ui, |ui| {
+            apply |= balance_sliders(ui, panel.active_mut());
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Preset");
+            ui.text_edit_singleline(&mut panel.preset_name);
+            let name = panel.preset_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save")).clicked() {
+                let path = preset_path(&name);
+                panel.status = match panel.active_mut().save(&path) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(err) => format!("Could not save {}: {}", path.display(), err),
+                };
+                panel.refresh_presets();
+            }
+        });
+        let response = egui::ComboBox::from_label("Load into slot")
+            .selected_text("choose a preset")
+            .show_ui(ui, |ui| {
+                let mut chosen = None;
+                for name in panel.presets.iter() {
+                    if ui.selectable_label(false, name).clicked() {
+                        chosen = Some(name.clone());
+                    }
+                }
+                chosen
+            });
+        if response.response.clicked() {
+            panel.refresh_presets();
+        }
+        if let Some(Some(name)) = response.inner {
+            let path = preset_path(&name);
+            match GameBalance::load(&path) {
+                Ok(loaded) => {
+                    *panel.active_mut() = loaded;
+                    panel.preset_name = name;
+                    panel.status = format!("Loaded {}", path.display());
+                    apply = true;
+                }
+                Err(err) => panel.status = format!("Could not load {}: {}", path.display(), err),
+            }
+        }
+        if !panel.status.is_empty() {
+            ui.label(&panel.status);
+        }
+    });
+
+    if apply {
+        balance.set_if_neq(panel.active_mut().clone());
+    }
+}
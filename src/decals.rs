@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+
+use crate::consts::*;
+use crate::swarm::{HatcheryDestroyed, SwarmlingDied};
+
+/// Blood splats and scorch marks that stay on the ground for the whole run.
+pub struct DecalPlugin;
+
+impl Plugin for DecalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DecalBudget>()
+            .add_event::<SpawnDecal>()
+            .add_systems(Update, ((swarmling_decals, hatchery_decals), spawn_decals).chain());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecalKind {
+    Blood,
+    Scorch,
+}
+
+impl DecalKind {
+    fn color(&self) -> Color {
+        match self {
+            DecalKind::Blood => Color::srgba(0.45, 0.02, 0.02, 0.8),
+            DecalKind::Scorch => Color::srgba(0.05, 0.04, 0.03, 0.7),
+        }
+    }
+
+    /// World-space diameter of the decal
+    fn size(&self) -> f32 {
+        match self {
+            DecalKind::Blood => 18.0,
+            DecalKind::Scorch => 110.0,
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SpawnDecal {
+    pub kind: DecalKind,
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+pub struct Decal;
+
+/// Live decal entities, oldest first. Once [`MAX_DECALS`] is reached the oldest one is
+/// moved to the new spot instead of spawning another entity.
+#[derive(Resource, Default)]
+pub struct DecalBudget {
+    decals: VecDeque<Entity>,
+}
+
+fn swarmling_decals(
+    mut swarmling_died_event_reader: EventReader<SwarmlingDied>,
+    mut spawn_decal_event_writer: EventWriter<SpawnDecal>,
+) {
+    for died in swarmling_died_event_reader.read() {
+        spawn_decal_event_writer.send(SpawnDecal {
+            kind: DecalKind::Blood,
+            position: died.position,
+        });
+    }
+}
+
+fn hatchery_decals(
+    mut hatchery_destroyed_event_reader: EventReader<HatcheryDestroyed>,
+    mut spawn_decal_event_writer: EventWriter<SpawnDecal>,
+) {
+    for destroyed in hatchery_destroyed_event_reader.read() {
+        spawn_decal_event_writer.send(SpawnDecal {
+            kind: DecalKind::Scorch,
+            position: destroyed.position,
+        });
+    }
+}
+
+fn spawn_decals(
+    mut commands: Commands,
+    mut spawn_decal_event_reader: EventReader<SpawnDecal>,
+    mut budget: ResMut<DecalBudget>,
+    mut q_decals: Query<(&mut Transform, &mut Sprite), With<Decal>>,
+    assets: Res<AssetServer>,
+) {
+    for event in spawn_decal_event_reader.read() {
+        // Decals have no collider and nothing in the simulation reads them, so the unseeded
+        // global RNG can vary their look without shifting the GameRng stream
+        let transform = Transform {
+            translation: event.position.extend(DECAL_Z),
+            rotation: Quat::from_rotation_z(fastrand::f32() * TAU),
+            scale: Vec3::splat(event.kind.size() / DECAL_TEXTURE_SIZE * (0.8 + fastrand::f32() * 0.4)),
+        };
+        let color = event.kind.color();
+
+        if budget.decals.len() >= MAX_DECALS {
+            if let Some(oldest) = budget.decals.pop_front() {
+                if let Ok((mut decal_transform, mut sprite)) = q_decals.get_mut(oldest) {
+                    *decal_transform = transform;
+                    sprite.color = color;
+                    budget.decals.push_back(oldest);
+                    continue;
+                }
+            }
+        }
+
+        let decal = commands
+            .spawn((
+                Decal,
+                Name::new("Decal"),
+                Sprite {
+                    image: assets.load("sprites/particles/dirt_01.png"),
+                    color,
+                    ..default()
+                },
+                transform,
+            ))
+            .id();
+        budget.decals.push_back(decal);
+    }
+}
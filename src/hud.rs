@@ -0,0 +1,252 @@
+use bevy::prelude::*;
+
+use crate::balance::GameBalance;
+use crate::consts::*;
+use crate::player::{Dash, Player};
+use crate::score::Score;
+use crate::swarm::{Swarmling, Wave};
+use crate::weapon::Weapon;
+
+const HUD_TEXT_SIZE: f32 = 20.;
+const BAR_WIDTH: f32 = 200.;
+const BAR_HEIGHT: f32 = 12.;
+const BAR_BACKGROUND_COLOR: Color = Color::srgba(0., 0., 0., 0.6);
+const HEALTH_COLOR: Color = Color::srgb(0.8, 0.15, 0.15);
+const DASH_READY_COLOR: Color = Color::srgb(0.3, 0.7, 1.0);
+const DASH_COOLDOWN_COLOR: Color = Color::srgb(0.3, 0.35, 0.45);
+
+/// Health, dash, weapon, wave, score and swarm size. Every element only updates when
+/// the value behind it changes.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_hud).add_systems(
+            Update,
+            (
+                hud_health,
+                hud_dash,
+                hud_weapon,
+                hud_wave.run_if(resource_changed::<Wave>),
+                hud_score,
+                hud_swarm_count,
+            ),
+        );
+    }
+}
+
+#[derive(Component)]
+struct HealthBar;
+
+#[derive(Component)]
+struct DashBar;
+
+#[derive(Component)]
+struct WeaponText;
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct SwarmCountText;
+
+fn hud_text(text: &str) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: HUD_TEXT_SIZE,
+            ..default()
+        },
+    )
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, label: &str, fill: impl Bundle) {
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(8.),
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn((
+                hud_text(label),
+                Node {
+                    width: Val::Px(60.),
+                    ..default()
+                },
+            ));
+            row.spawn((
+                Node {
+                    width: Val::Px(BAR_WIDTH),
+                    height: Val::Px(BAR_HEIGHT),
+                    ..default()
+                },
+                BackgroundColor(BAR_BACKGROUND_COLOR),
+            ))
+            .with_children(|bar| {
+                bar.spawn(fill);
+            });
+        });
+}
+
+fn spawn_hud(mut commands: Commands) {
+    // Player status, bottom left
+    commands
+        .spawn((
+            Name::new("Hud"),
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(16.),
+                bottom: Val::Px(16.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            spawn_bar(
+                parent,
+                "Health",
+                (
+                    HealthBar,
+                    Node {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    BackgroundColor(HEALTH_COLOR),
+                ),
+            );
+            spawn_bar(
+                parent,
+                "Dash",
+                (
+                    DashBar,
+                    Node {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    BackgroundColor(DASH_READY_COLOR),
+                ),
+            );
+            parent.spawn((WeaponText, hud_text("")));
+        });
+
+    // Run status, top right
+    commands
+        .spawn((
+            Name::new("Hud"),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(16.),
+                top: Val::Px(16.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                row_gap: Val::Px(4.),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((ScoreText, hud_text("0")));
+            parent.spawn((WaveText, hud_text("Wave 1")));
+            parent.spawn((SwarmCountText, hud_text("Swarmlings: 0")));
+        });
+}
+
+fn hud_health(
+    q_player: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut q_bar: Query<&mut Node, With<HealthBar>>,
+    balance: Res<GameBalance>,
+) {
+    let Ok(health) = q_player.get_single() else {
+        return;
+    };
+    for mut node in q_bar.iter_mut() {
+        node.width = Val::Percent((health.0 / balance.player.health).clamp(0., 1.) * 100.);
+    }
+}
+
+fn hud_dash(
+    q_player: Query<&Dash, (With<Player>, Changed<Dash>)>,
+    mut q_bar: Query<(&mut Node, &mut BackgroundColor), With<DashBar>>,
+) {
+    let Ok(dash) = q_player.get_single() else {
+        return;
+    };
+    let ready = 1. - dash.cooldown_fraction();
+    for (mut node, mut color) in q_bar.iter_mut() {
+        node.width = Val::Percent(ready * 100.);
+        color.0 = if ready >= 1. && !dash.is_dashing() {
+            DASH_READY_COLOR
+        } else {
+            DASH_COOLDOWN_COLOR
+        };
+    }
+}
+
+fn hud_weapon(
+    q_player: Query<&Weapon, (With<Player>, Changed<Weapon>)>,
+    mut q_text: Query<&mut Text, With<WeaponText>>,
+) {
+    let Ok(weapon) = q_player.get_single() else {
+        return;
+    };
+    let text = match weapon.reload_progress() {
+        Some(_) => format!("{}  reloading", weapon.name),
+        None => format!("{}  {} / {}", weapon.name, weapon.ammo, weapon.magazine_size),
+    };
+    for mut weapon_text in q_text.iter_mut() {
+        // Reload progress changes every tick, so skip writes that wouldn't change the text
+        if weapon_text.0 != text {
+            weapon_text.0 = text.clone();
+        }
+    }
+}
+
+fn hud_wave(wave: Res<Wave>, mut q_text: Query<&mut Text, With<WaveText>>) {
+    for mut text in q_text.iter_mut() {
+        text.0 = format!("Wave {}", wave.0);
+    }
+}
+
+/// `Score` changes every tick with the run clock, so this compares what it shows instead.
+fn hud_score(
+    score: Res<Score>,
+    balance: Res<GameBalance>,
+    mut shown: Local<Option<(u64, u32)>>,
+    mut q_text: Query<&mut Text, With<ScoreText>>,
+) {
+    let multiplier = score.multiplier(&balance.score);
+    if *shown == Some((score.points, multiplier)) {
+        return;
+    }
+    *shown = Some((score.points, multiplier));
+    let text = if multiplier > 1 {
+        format!("{}  x{}", score.points, multiplier)
+    } else {
+        score.points.to_string()
+    };
+    for mut score_text in q_text.iter_mut() {
+        score_text.0 = text.clone();
+    }
+}
+
+fn hud_swarm_count(
+    q_added: Query<(), Added<Swarmling>>,
+    mut removed: RemovedComponents<Swarmling>,
+    q_swarmling: Query<(), With<Swarmling>>,
+    mut q_text: Query<&mut Text, With<SwarmCountText>>,
+) {
+    if q_added.is_empty() && removed.read().next().is_none() {
+        return;
+    }
+    removed.clear();
+    for mut text in q_text.iter_mut() {
+        text.0 = format!("Swarmlings: {}", q_swarmling.iter().count());
+    }
+}
@@ -1,75 +1,524 @@
-use avian2d::math::Scalar;
-use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
-use bevy::input::{ButtonInput, ButtonState};
+use bevy::input::gamepad::{GamepadAxis, GamepadButton};
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::input::ButtonInput;
 use bevy::math::Vec2;
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::Path;
 
-#[derive(Event)]
+use crate::consts::*;
+use crate::player::Player;
+
+#[derive(Event, Clone, Debug, Serialize, Deserialize)]
 pub enum PlayerMovementEvent {
     Move(Vec2),
     Dash(Vec2),
 }
 
-#[derive(Event)]
+#[derive(Event, Clone, Debug, Serialize, Deserialize)]
 pub enum PlayerAttackEvent {
     PrimaryFire,
+    SecondaryFire,
 }
 
+/// World-space direction the player wants to aim in.
+#[derive(Event, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PlayerAimEvent(pub Vec2);
+
 #[derive(Event)]
 pub enum CameraEvent {
     Zoom(f32),
 }
 
-/// Sends [`PlayerMovementEvent`] events based on keyboard input.
-pub fn keyboard_input(
-    mut movement_event_writer: EventWriter<PlayerMovementEvent>,
+/// Everything the player can do, independent of the device it is bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Dash,
+    PrimaryFire,
+    SecondaryFire,
+    ZoomIn,
+    ZoomOut,
+    Pause,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 10] = [
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Dash,
+        InputAction::PrimaryFire,
+        InputAction::SecondaryFire,
+        InputAction::ZoomIn,
+        InputAction::ZoomOut,
+        InputAction::Pause,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputAction::MoveUp => "Move up",
+            InputAction::MoveDown => "Move down",
+            InputAction::MoveLeft => "Move left",
+            InputAction::MoveRight => "Move right",
+            InputAction::Dash => "Dash",
+            InputAction::PrimaryFire => "Primary fire",
+            InputAction::SecondaryFire => "Secondary fire",
+            InputAction::ZoomIn => "Zoom in",
+            InputAction::ZoomOut => "Zoom out",
+            InputAction::Pause => "Pause",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WheelDirection {
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A single physical input that can trigger an [`InputAction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    MouseWheel(WheelDirection),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis, AxisDirection),
+}
+
+/// The device the player used last, which decides between mouse and stick aiming.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad,
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(button) => write!(f, "Mouse {:?}", button),
+            Binding::MouseWheel(WheelDirection::Up) => write!(f, "Wheel up"),
+            Binding::MouseWheel(WheelDirection::Down) => write!(f, "Wheel down"),
+            Binding::GamepadButton(button) => write!(f, "Pad {:?}", button),
+            Binding::GamepadAxis(axis, AxisDirection::Positive) => write!(f, "Pad {:?}+", axis),
+            Binding::GamepadAxis(axis, AxisDirection::Negative) => write!(f, "Pad {:?}-", axis),
+        }
+    }
+}
+
+/// Maps every [`InputAction`] to any number of [`Binding`]s.
+/// Loaded from and saved to [`INPUT_MAP_PATH`].
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct InputMap {
+    pub bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use AxisDirection::*;
+        let key = Binding::Key;
+        let pad = Binding::GamepadButton;
+        let stick = Binding::GamepadAxis;
+        let mut bindings = BTreeMap::new();
+        bindings.insert(
+            InputAction::MoveUp,
+            vec![key(KeyCode::KeyW), key(KeyCode::ArrowUp), stick(GamepadAxis::LeftStickY, Positive)],
+        );
+        bindings.insert(
+            InputAction::MoveDown,
+            vec![key(KeyCode::KeyS), key(KeyCode::ArrowDown), stick(GamepadAxis::LeftStickY, Negative)],
+        );
+        bindings.insert(
+            InputAction::MoveLeft,
+            vec![key(KeyCode::KeyA), key(KeyCode::ArrowLeft), stick(GamepadAxis::LeftStickX, Negative)],
+        );
+        bindings.insert(
+            InputAction::MoveRight,
+            vec![key(KeyCode::KeyD), key(KeyCode::ArrowRight), stick(GamepadAxis::LeftStickX, Positive)],
+        );
+        bindings.insert(InputAction::Dash, vec![key(KeyCode::Space), pad(GamepadButton::South)]);
+        bindings.insert(
+            InputAction::PrimaryFire,
+            vec![Binding::Mouse(MouseButton::Left), pad(GamepadButton::RightTrigger2)],
+        );
+        bindings.insert(
+            InputAction::SecondaryFire,
+            vec![Binding::Mouse(MouseButton::Right), pad(GamepadButton::LeftTrigger2)],
+        );
+        bindings.insert(
+            InputAction::ZoomIn,
+            vec![Binding::MouseWheel(WheelDirection::Up), pad(GamepadButton::DPadUp)],
+        );
+        bindings.insert(
+            InputAction::ZoomOut,
+            vec![Binding::MouseWheel(WheelDirection::Down), pad(GamepadButton::DPadDown)],
+        );
+        bindings.insert(InputAction::Pause, vec![key(KeyCode::Escape), pad(GamepadButton::Start)]);
+        InputMap { bindings }
+    }
+}
+
+impl InputMap {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, action: InputAction) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Adds `binding` to `action`, removing it from any other action first.
+    pub fn bind(&mut self, action: InputAction, binding: Binding) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        self.bindings.entry(action).or_default().push(binding);
+    }
+
+    pub fn clear(&mut self, action: InputAction) {
+        self.bindings.remove(&action);
+    }
+}
+
+/// Per-frame state of every [`InputAction`], built from the [`InputMap`].
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<InputAction>,
+    previous: HashSet<InputAction>,
+    analog: HashMap<InputAction, f32>,
+    /// Right stick aim direction, if the stick is outside its deadzone.
+    pub aim: Option<Vec2>,
+    pub device: InputDevice,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action) && !self.previous.contains(&action)
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        !self.pressed.contains(&action) && self.previous.contains(&action)
+    }
+
+    /// Analog contribution of an action this frame, e.g. mouse wheel lines.
+    pub fn analog(&self, action: InputAction) -> f32 {
+        self.analog.get(&action).copied().unwrap_or(0.0)
+    }
+
+    /// 1.0 while a digital binding is held, otherwise the analog value.
+    pub fn value(&self, action: InputAction) -> f32 {
+        if self.pressed(action) {
+            1.0
+        } else {
+            self.analog(action)
+        }
+    }
+
+    pub fn move_direction(&self) -> Vec2 {
+        let direction = Vec2::new(
+            self.value(InputAction::MoveRight) - self.value(InputAction::MoveLeft),
+            self.value(InputAction::MoveUp) - self.value(InputAction::MoveDown),
+        );
+        direction.clamp_length_max(1.0)
+    }
+
+    pub fn press(&mut self, action: InputAction) {
+        self.pressed.insert(action);
+    }
+
+    pub fn add_analog(&mut self, action: InputAction, value: f32) {
+        *self.analog.entry(action).or_default() += value;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferedAction {
+    Dash(Vec2),
+    PrimaryFire,
+    SecondaryFire,
+}
+
+/// Presses waiting to be consumed by the fixed-step gameplay systems.
+///
+/// A press that can't be acted on yet, like a dash during its cooldown, stays queued
+/// for [`INPUT_BUFFER_WINDOW`] seconds instead of being dropped.
+#[derive(Resource, Default, Debug)]
+pub struct InputBuffer {
+    queue: VecDeque<(BufferedAction, f32)>,
+}
+
+impl InputBuffer {
+    pub fn push(&mut self, action: BufferedAction) {
+        self.queue.push_back((action, 0.0));
+    }
+
+    /// Removes and returns the oldest queued action matching `predicate`.
+    pub fn take(&mut self, predicate: impl Fn(&BufferedAction) -> bool) -> Option<BufferedAction> {
+        let index = self.queue.iter().position(|(action, _)| predicate(action))?;
+        self.queue.remove(index).map(|(action, _)| action)
+    }
+
+    pub fn take_dash(&mut self) -> Option<Vec2> {
+        match self.take(|action| matches!(action, BufferedAction::Dash(_))) {
+            Some(BufferedAction::Dash(direction)) => Some(direction),
+            _ => None,
+        }
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        for (_, age) in self.queue.iter_mut() {
+            *age += delta;
+        }
+        self.queue.retain(|(_, age)| *age <= INPUT_BUFFER_WINDOW);
+    }
+}
+
+/// Moves the dash and attack presses that reached this fixed tick into the [`InputBuffer`]
+/// and expires the ones that waited too long.
+pub fn buffer_player_input(
+    mut input_buffer: ResMut<InputBuffer>,
+    mut movement_event_reader: EventReader<PlayerMovementEvent>,
+    mut attack_event_reader: EventReader<PlayerAttackEvent>,
+    time: Res<Time>,
+) {
+    input_buffer.tick(time.delta_secs());
+    for event in movement_event_reader.read() {
+        if let PlayerMovementEvent::Dash(direction) = event {
+            input_buffer.push(BufferedAction::Dash(*direction));
+        }
+    }
+    for event in attack_event_reader.read() {
+        match event {
+            PlayerAttackEvent::PrimaryFire => input_buffer.push(BufferedAction::PrimaryFire),
+            PlayerAttackEvent::SecondaryFire => input_buffer.push(BufferedAction::SecondaryFire),
+        }
+    }
+}
+
+/// Applies a radial deadzone to a stick and rescales the remaining range to `0..=1`.
+pub fn stick_with_deadzone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length < GAMEPAD_STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - GAMEPAD_STICK_DEADZONE) / (1.0 - GAMEPAD_STICK_DEADZONE)).min(1.0);
+    stick / length * scaled
+}
+
+fn gamepad_axis_value(gamepad: &Gamepad, axis: GamepadAxis) -> f32 {
+    let left = stick_with_deadzone(gamepad.left_stick());
+    let right = stick_with_deadzone(gamepad.right_stick());
+    match axis {
+        GamepadAxis::LeftStickX => left.x,
+        GamepadAxis::LeftStickY => left.y,
+        GamepadAxis::RightStickX => right.x,
+        GamepadAxis::RightStickY => right.y,
+        other => gamepad.get(other).unwrap_or(0.0),
+    }
+}
+
+/// Rebuilds the [`ActionState`] from the raw device input using the current [`InputMap`].
+pub fn update_action_state(
+    mut action_state: ResMut<ActionState>,
+    input_map: Res<InputMap>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    mut mouse_wheel_event: EventReader<MouseWheel>,
+    mut cursor_moved_event: EventReader<CursorMoved>,
+    q_gamepads: Query<&Gamepad>,
 ) {
-    let left = keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
-    let right = keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
-    let up = keyboard_input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]);
-    let down = keyboard_input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]);
-    let dash = keyboard_input.any_pressed([KeyCode::Space]);
+    let action_state = action_state.as_mut();
+    action_state.previous = std::mem::take(&mut action_state.pressed);
+    action_state.analog.clear();
+    action_state.aim = None;
+
+    let mouse_used = cursor_moved_event.read().count() > 0
+        || keyboard_input.get_just_pressed().next().is_some()
+        || mouse_button_input.get_just_pressed().next().is_some();
+    if mouse_used {
+        action_state.device = InputDevice::KeyboardMouse;
+    }
+
+    for gamepad in q_gamepads.iter() {
+        let aim = stick_with_deadzone(gamepad.right_stick());
+        if aim != Vec2::ZERO {
+            action_state.aim = Some(aim.normalize());
+        }
+        let gamepad_used = aim != Vec2::ZERO
+            || stick_with_deadzone(gamepad.left_stick()) != Vec2::ZERO
+            || gamepad.get_just_pressed().next().is_some();
+        if gamepad_used && !mouse_used {
+            action_state.device = InputDevice::Gamepad;
+        }
+    }
+
+    let mut wheel = 0.0;
+    for event in mouse_wheel_event.read() {
+        wheel += match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.,
+        };
+    }
 
-    let horizontal = right as i8 - left as i8;
-    let vertical = up as i8 - down as i8;
+    for (action, bindings) in input_map.bindings.iter() {
+        for binding in bindings {
+            match binding {
+                Binding::Key(key) => {
+                    if keyboard_input.pressed(*key) {
+                        action_state.press(*action);
+                    }
+                }
+                Binding::Mouse(button) => {
+                    if mouse_button_input.pressed(*button) {
+                        action_state.press(*action);
+                    }
+                }
+                Binding::MouseWheel(WheelDirection::Up) => {
+                    if wheel > 0.0 {
+                        action_state.add_analog(*action, wheel);
+                    }
+                }
+                Binding::MouseWheel(WheelDirection::Down) => {
+                    if wheel < 0.0 {
+                        action_state.add_analog(*action, -wheel);
+                    }
+                }
+                Binding::GamepadButton(button) => {
+                    if q_gamepads.iter().any(|gamepad| gamepad.pressed(*button)) {
+                        action_state.press(*action);
+                    }
+                }
+                Binding::GamepadAxis(axis, direction) => {
+                    for gamepad in q_gamepads.iter() {
+                        let value = gamepad_axis_value(gamepad, *axis);
+                        let value = match direction {
+                            AxisDirection::Positive => value.max(0.0),
+                            AxisDirection::Negative => (-value).max(0.0),
+                        };
+                        action_state.add_analog(*action, value);
+                    }
+                }
+            }
+        }
+    }
+}
 
-    let direction = Vec2::new(horizontal as Scalar, vertical as Scalar);
+/// Sends [`PlayerMovementEvent`] events based on the action state.
+pub fn movement_input(
+    mut movement_event_writer: EventWriter<PlayerMovementEvent>,
+    action_state: Res<ActionState>,
+) {
+    let direction = action_state.move_direction();
 
     movement_event_writer.send(PlayerMovementEvent::Move(direction));
-    if dash {
+    if action_state.just_pressed(InputAction::Dash) {
         movement_event_writer.send(PlayerMovementEvent::Dash(direction));
     }
 }
 
-pub fn mouse_input(
-    mut mouse_btn_event: EventReader<MouseButtonInput>,
+/// Sends [`PlayerAttackEvent`] events based on the action state.
+pub fn attack_input(
     mut attack_event_writer: EventWriter<PlayerAttackEvent>,
+    action_state: Res<ActionState>,
 ) {
-    for event in mouse_btn_event.read() {
-        if event.state == ButtonState::Pressed {
-            match event.button {
-                MouseButton::Left => {
-                    attack_event_writer.send(PlayerAttackEvent::PrimaryFire);
-                }
-                _ => continue,
+    if action_state.just_pressed(InputAction::PrimaryFire) {
+        attack_event_writer.send(PlayerAttackEvent::PrimaryFire);
+    }
+    if action_state.just_pressed(InputAction::SecondaryFire) {
+        attack_event_writer.send(PlayerAttackEvent::SecondaryFire);
+    }
+}
+
+/// Sends [`PlayerAimEvent`] events from the right stick or the cursor, depending on
+/// which device was used last.
+pub fn aim_input(
+    mut aim_event_writer: EventWriter<PlayerAimEvent>,
+    action_state: Res<ActionState>,
+    q_player: Query<&Transform, With<Player>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    if action_state.device == InputDevice::Gamepad {
+        // Keep the last aim direction while the right stick is centered
+        if let Some(direction) = action_state.aim {
+            aim_event_writer.send(PlayerAimEvent(direction));
+        }
+        return;
+    }
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    if let Ok(window) = q_window.get_single() {
+        if let Some(cursor_position) = window.cursor_position() {
+            let (camera, camera_global_transform) = q_camera.single();
+            if let Ok(cursor_world_position) =
+                camera.viewport_to_world_2d(camera_global_transform, cursor_position)
+            {
+                let direction = cursor_world_position - player_transform.translation.truncate();
+                aim_event_writer.send(PlayerAimEvent(direction));
             }
         }
     }
 }
 
-pub fn mouse_wheel_input(
-    mut mouse_wheel_event: EventReader<MouseWheel>,
+/// Sends [`CameraEvent`] events based on the action state.
+pub fn camera_input(
     mut camera_event_writer: EventWriter<CameraEvent>,
+    action_state: Res<ActionState>,
+    time: Res<Time>,
 ) {
-    for event in mouse_wheel_event.read() {
-        match event.unit {
-            MouseScrollUnit::Line => {
-                camera_event_writer.send(CameraEvent::Zoom(event.y));
-            }
-            MouseScrollUnit::Pixel => {
-                camera_event_writer.send(CameraEvent::Zoom(event.y / 100.));
+    // Held buttons zoom at a fixed rate, the wheel zooms by the scrolled amount
+    let held = action_state.pressed(InputAction::ZoomIn) as i8
+        - action_state.pressed(InputAction::ZoomOut) as i8;
+    let zoom = action_state.analog(InputAction::ZoomIn) - action_state.analog(InputAction::ZoomOut)
+        + held as f32 * CAMERA_KEY_ZOOM_SPEED * time.delta_secs();
+    if zoom != 0.0 {
+        camera_event_writer.send(CameraEvent::Zoom(zoom));
+    }
+}
+
+/// Replaces the default [`InputMap`] with the one saved at [`INPUT_MAP_PATH`],
+/// writing the defaults there on first launch.
+pub fn load_input_map(mut input_map: ResMut<InputMap>) {
+    match InputMap::load(INPUT_MAP_PATH) {
+        Ok(loaded) => *input_map = loaded,
+        Err(_) if !Path::new(INPUT_MAP_PATH).exists() => {
+            if let Err(err) = input_map.save(INPUT_MAP_PATH) {
+                warn!("Could not save input map to {}: {}", INPUT_MAP_PATH, err);
             }
         }
+        Err(err) => warn!("Could not load input map from {}: {}", INPUT_MAP_PATH, err),
     }
 }
@@ -0,0 +1,515 @@
+use avian2d::prelude::*;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, RecursiveDependencyLoadState};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::arena::{spawn_arena, ArenaSource};
+use crate::balance::GameBalance;
+use crate::consts::*;
+use crate::player::Player;
+use crate::swarm::{hatchery_bundle, HatcherySettings};
+
+/// Loads hand-authored levels from Tiled JSON maps (`.tmj`) with embedded tilesets.
+///
+/// Tile layers become sprites, objects on a layer named `Collision` become static colliders,
+/// and objects with the class `PlayerSpawn`, `Hatchery` or `Pickup` become entity markers.
+/// Hatcheries read the `spawn_interval` and `health` float properties, pickups a `kind` string.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelMap>()
+            .init_asset_loader::<TiledMapLoader>()
+            .register_type::<Pickup>()
+            .add_systems(Update, spawn_level.run_if(resource_exists::<PendingLevel>));
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+#[require(Name(|| "Pickup"))]
+pub struct Pickup {
+    pub kind: String,
+}
+
+/// A level that is still loading and will be spawned once it is ready.
+#[derive(Resource)]
+pub struct PendingLevel(pub Handle<LevelMap>);
+
+#[derive(Debug, Clone)]
+pub enum LevelShape {
+    Rectangle(Vec2),
+    Ellipse(Vec2),
+    Polygon(Vec<Vec2>),
+}
+
+impl LevelShape {
+    fn collider(&self) -> Option<Collider> {
+        match self {
+            LevelShape::Rectangle(size) => Some(Collider::rectangle(size.x, size.y)),
+            LevelShape::Ellipse(size) => Some(Collider::ellipse(size.x * 0.5, size.y * 0.5)),
+            LevelShape::Polygon(points) => {
+                if points.len() < 3 {
+                    return None;
+                }
+                // Tiled polygons can be concave, so they are split into convex parts rather
+                // than wrapped in their hull
+                let count = points.len() as u32;
+                let edges = (0..count).map(|i| [i, (i + 1) % count]).collect();
+                Some(Collider::convex_decomposition(points.clone(), edges))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelTile {
+    pub position: Vec2,
+    pub z: f32,
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub index: usize,
+}
+
+#[derive(Asset, TypePath, Debug, Default)]
+pub struct LevelMap {
+    pub tiles: Vec<LevelTile>,
+    pub colliders: Vec<(Vec2, LevelShape)>,
+    pub player_spawn: Vec2,
+    pub hatcheries: Vec<(Vec2, HatcherySettings)>,
+    pub pickups: Vec<(Vec2, String)>,
+}
+
+#[derive(Deserialize)]
+struct TiledMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TiledLayer {
+    Tilelayer {
+        #[serde(default)]
+        data: Vec<u32>,
+        width: u32,
+        #[serde(default = "default_visible")]
+        visible: bool,
+    },
+    Objectgroup {
+        name: String,
+        objects: Vec<TiledObject>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    /// Called `type` before Tiled 1.9
+    #[serde(default, alias = "type")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    polygon: Option<Vec<TiledPoint>>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+impl TiledObject {
+    fn property(&self, name: &str) -> Option<&serde_json::Value> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| &property.value)
+    }
+
+    fn float_property(&self, name: &str) -> Option<f32> {
+        self.property(name)
+            .and_then(|value| value.as_f64())
+            .map(|value| value as f32)
+    }
+}
+
+/// Flip and rotation flags stored in the top bits of a tile gid.
+const TILED_GID_MASK: u32 = 0x1FFF_FFFF;
+
+/// A tileset's first gid, image and atlas layout.
+type LoadedTileset = (u32, Handle<Image>, Handle<TextureAtlasLayout>);
+
+impl TiledTileset {
+    fn layout(&self) -> TextureAtlasLayout {
+        let rows = self.tilecount.div_ceil(self.columns.max(1));
+        TextureAtlasLayout::from_grid(
+            UVec2::new(self.tilewidth, self.tileheight),
+            self.columns,
+            rows,
+            Some(UVec2::splat(self.spacing)),
+            Some(UVec2::splat(self.margin)),
+        )
+    }
+}
+
+/// Turns the layers of `map` into tiles, colliders and markers, centered on the origin.
+fn build_level(map: &TiledMap, tilesets: &[LoadedTileset]) -> LevelMap {
+    let map_size = Vec2::new(
+        (map.width * map.tilewidth) as f32,
+        (map.height * map.tileheight) as f32,
+    );
+    // Tiled is y-down from the top left corner, the level is centered on the origin
+    let to_world = |x: f32, y: f32| Vec2::new(x - map_size.x * 0.5, map_size.y * 0.5 - y);
+
+    let mut level = LevelMap::default();
+    for (layer_index, layer) in map.layers.iter().enumerate() {
+        match layer {
+            TiledLayer::Tilelayer {
+                data,
+                width,
+                visible,
+            } => {
+                if !visible {
+                    continue;
+                }
+                for (cell, raw_gid) in data.iter().enumerate() {
+                    let gid = raw_gid & TILED_GID_MASK;
+                    if gid == 0 {
+                        continue;
+                    }
+                    let Some((firstgid, image, layout)) = tilesets
+                        .iter()
+                        .filter(|(firstgid, _, _)| *firstgid <= gid)
+                        .max_by_key(|(firstgid, _, _)| *firstgid)
+                    else {
+                        continue;
+                    };
+                    let column = cell as u32 % width;
+                    let row = cell as u32 / width;
+                    level.tiles.push(LevelTile {
+                        position: to_world(
+                            (column as f32 + 0.5) * map.tilewidth as f32,
+                            (row as f32 + 0.5) * map.tileheight as f32,
+                        ),
+                        z: ARENA_GROUND_Z + layer_index as f32 * 0.01,
+                        image: image.clone(),
+                        layout: layout.clone(),
+                        index: (gid - firstgid) as usize,
+                    });
+                }
+            }
+            TiledLayer::Objectgroup { name, objects } => {
+                for object in objects {
+                    if object.rotation != 0.0 {
+                        warn!("Ignoring rotation of level object {:?}", object.name);
+                    }
+                    let center = to_world(
+                        object.x + object.width * 0.5,
+                        object.y + object.height * 0.5,
+                    );
+                    match object.class.as_str() {
+                        "PlayerSpawn" => level.player_spawn = center,
+                        "Hatchery" => {
+                            level.hatcheries.push((
+                                center,
+                                HatcherySettings {
+                                    spawn_interval: object.float_property("spawn_interval"),
+                                    health: object.float_property("health"),
+                                },
+                            ));
+                        }
+                        "Pickup" => {
+                            let kind = object
+                                .property("kind")
+                                .and_then(|value| value.as_str())
+                                .unwrap_or(&object.name);
+                            level.pickups.push((center, String::from(kind)));
+                        }
+                        _ if name.eq_ignore_ascii_case("collision") => {
+                            if let Some(points) = &object.polygon {
+                                let origin = to_world(object.x, object.y);
+                                let points = points
+                                    .iter()
+                                    .map(|point| Vec2::new(point.x, -point.y))
+                                    .collect();
+                                level.colliders.push((origin, LevelShape::Polygon(points)));
+                            } else if object.ellipse {
+                                let size = Vec2::new(object.width, object.height);
+                                level.colliders.push((center, LevelShape::Ellipse(size)));
+                            } else {
+                                let size = Vec2::new(object.width, object.height);
+                                level.colliders.push((center, LevelShape::Rectangle(size)));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            TiledLayer::Other => {}
+        }
+    }
+    level
+}
+
+#[derive(Default)]
+pub struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    type Asset = LevelMap;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let map: TiledMap = serde_json::from_slice(&bytes)?;
+
+        let mut tilesets = Vec::new();
+        for (index, tileset) in map.tilesets.iter().enumerate() {
+            let Some(image) = &tileset.image else {
+                return Err(format!(
+                    "tileset {:?} is external or an image collection, only embedded single-image tilesets are supported",
+                    tileset.source
+                )
+                .into());
+            };
+            let image_path = load_context.asset_path().resolve_embed(image)?;
+            tilesets.push((
+                tileset.firstgid,
+                load_context.load::<Image>(image_path),
+                load_context.add_labeled_asset(format!("tileset{}", index), tileset.layout()),
+            ));
+        }
+        Ok(build_level(&map, &tilesets))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    pending: Res<PendingLevel>,
+    levels: Res<Assets<LevelMap>>,
+    assets: Res<AssetServer>,
+    balance: Res<GameBalance>,
+    mut q_player: Query<&mut Transform, With<Player>>,
+) {
+    match assets.get_recursive_dependency_load_state(&pending.0) {
+        Some(RecursiveDependencyLoadState::Loaded) => {}
+        Some(RecursiveDependencyLoadState::Failed(err)) => {
+            error!("Could not load level, generating an arena instead: {}", err);
+            commands.remove_resource::<PendingLevel>();
+            commands.insert_resource(ArenaSource::Generated);
+            commands.run_system_cached(spawn_arena);
+            return;
+        }
+        _ => return,
+    }
+    commands.remove_resource::<PendingLevel>();
+    let Some(level) = levels.get(&pending.0) else {
+        return;
+    };
+    info!(
+        "Spawning level: {} tiles, {} colliders, {} hatcheries, {} pickups",
+        level.tiles.len(),
+        level.colliders.len(),
+        level.hatcheries.len(),
+        level.pickups.len()
+    );
+
+    for tile in level.tiles.iter() {
+        commands.spawn((
+            Sprite::from_atlas_image(
+                tile.image.clone(),
+                TextureAtlas {
+                    layout: tile.layout.clone(),
+                    index: tile.index,
+                },
+            ),
+            Transform::from_translation(tile.position.extend(tile.z)),
+        ));
+    }
+
+    for (position, shape) in level.colliders.iter() {
+        let Some(collider) = shape.collider() else {
+            warn!("Skipping degenerate level collider at {}", position);
+            continue;
+        };
+        commands.spawn((
+            Name::new("Level collider"),
+            Transform::from_translation(position.extend(0.)),
+            RigidBody::Static,
+            collider,
+        ));
+    }
+
+    for (position, settings) in level.hatcheries.iter() {
+        commands.spawn(hatchery_bundle(&assets, *position, *settings, &balance.hatchery));
+    }
+
+    for (position, kind) in level.pickups.iter() {
+        commands.spawn((
+            Pickup { kind: kind.clone() },
+            Sprite::from_color(Color::srgb(1.0, 0.85, 0.2), Vec2::splat(10.)),
+            Transform::from_translation(position.extend(0.)),
+            Collider::circle(10.),
+            Sensor,
+        ));
+    }
+
+    if let Ok(mut player_transform) = q_player.get_single_mut() {
+        player_transform.translation = level.player_spawn.extend(player_transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yard_map() -> TiledMap {
+        serde_json::from_str(include_str!("../assets/levels/yard.tmj")).unwrap()
+    }
+
+    fn yard() -> LevelMap {
+        let map = yard_map();
+        let tilesets: Vec<LoadedTileset> = map
+            .tilesets
+            .iter()
+            .map(|tileset| (tileset.firstgid, Handle::default(), Handle::default()))
+            .collect();
+        build_level(&map, &tilesets)
+    }
+
+    #[test]
+    fn tile_layers_become_tiles() {
+        let level = yard();
+        // Empty cells and the hidden layer are skipped
+        assert_eq!(level.tiles.len(), 19);
+        let first = &level.tiles[0];
+        assert_eq!(first.position, Vec2::new(-80., 48.));
+        assert_eq!(first.index, 0);
+        assert_eq!(first.z, ARENA_GROUND_Z);
+        // Flip flags are stripped from the gid
+        let flipped = level
+            .tiles
+            .iter()
+            .find(|tile| tile.position == Vec2::new(16., 16.))
+            .unwrap();
+        assert_eq!(flipped.index, 1);
+    }
+
+    #[test]
+    fn tileset_layout_matches_grid() {
+        let layout = yard_map().tilesets[0].layout();
+        assert_eq!(layout.textures.len(), 2);
+        assert_eq!(layout.textures[1].min, UVec2::new(32, 0));
+    }
+
+    #[test]
+    fn collision_objects_become_shapes() {
+        let level = yard();
+        assert_eq!(level.colliders.len(), 3);
+
+        let (position, shape) = &level.colliders[0];
+        assert_eq!(*position, Vec2::new(-80., 32.));
+        assert!(matches!(shape, LevelShape::Rectangle(size) if *size == Vec2::new(32., 64.)));
+
+        let (position, shape) = &level.colliders[1];
+        assert_eq!(*position, Vec2::new(80., -40.));
+        assert!(matches!(shape, LevelShape::Ellipse(size) if *size == Vec2::new(32., 16.)));
+
+        let (position, shape) = &level.colliders[2];
+        assert_eq!(*position, Vec2::new(-32., 32.));
+        let LevelShape::Polygon(points) = shape else {
+            panic!("expected a polygon, got {:?}", shape);
+        };
+        assert_eq!(points[2], Vec2::new(64., -16.));
+        assert_eq!(points[4], Vec2::new(16., -64.));
+    }
+
+    #[test]
+    fn concave_polygon_keeps_its_notch() {
+        let level = yard();
+        let collider = level.colliders[2].1.collider().unwrap();
+        assert!(collider.contains_point(Vector::ZERO, Rotation::default(), Vec2::new(8., -32.)));
+        // Inside the hull of the L shape, but not the shape itself
+        assert!(!collider.contains_point(Vector::ZERO, Rotation::default(), Vec2::new(40., -40.)));
+    }
+
+    #[test]
+    fn markers_become_spawns() {
+        let level = yard();
+        assert_eq!(level.player_spawn, Vec2::new(32., 0.));
+
+        assert_eq!(level.hatcheries.len(), 1);
+        let (position, settings) = level.hatcheries[0];
+        assert_eq!(position, Vec2::new(64., 32.));
+        assert_eq!(settings.spawn_interval, Some(2.5));
+        assert_eq!(settings.health, None);
+
+        // Without a `kind` property the pickup falls back to its name
+        assert_eq!(
+            level.pickups,
+            vec![(Vec2::new(-56., -40.), String::from("medkit"))]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use avian2d::prelude::*;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy_hanabi::prelude::*;
+
+pub mod arena;
+pub mod balance;
+pub mod bot;
+pub mod camera;
+pub mod consts;
+pub mod crosshair;
+pub mod decals;
+pub mod hud;
+pub mod input;
+pub mod level;
+pub mod menu;
+pub mod particles;
+pub mod player;
+pub mod projectile;
+pub mod replay;
+pub mod save;
+pub mod score;
+pub mod swarm;
+pub mod telemetry;
+pub mod weapon;
+
+#[cfg(debug_assertions)]
+pub mod debug;
+
+use arena::*;
+use balance::*;
+use bot::*;
+use camera::*;
+use consts::*;
+use crosshair::*;
+use decals::*;
+use hud::*;
+use input::*;
+use level::*;
+use menu::*;
+use particles::*;
+use player::*;
+use projectile::*;
+use replay::*;
+use save::*;
+use score::*;
+use swarm::*;
+use telemetry::*;
+use weapon::*;
+
+/// The simulation: player, swarm, projectiles, arena, score and saves. Needs no window,
+/// renderer or input devices, only physics and the asset server.
+pub struct SwarmyGamePlugin;
+
+impl Plugin for SwarmyGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            BalancePlugin,
+            LevelPlugin,
+            ScorePlugin,
+            SaveGamePlugin,
+            BotPlugin,
+            TelemetryPlugin,
+        ))
+            .init_state::<AppState>()
+            .register_type::<(Health, Projectile, SwarmSpawner, Hatchery, Player, Weapon, Wave)>()
+            .add_event::<PlayerMovementEvent>()
+            .add_event::<PlayerAttackEvent>()
+            .add_event::<PlayerAimEvent>()
+            .add_event::<PlayerDamageEvent>()
+            .add_event::<PlayerDeathEvent>()
+            .add_event::<ProjectileHitEvent>()
+            .add_event::<ProjectileFiredEvent>()
+            .add_event::<HatcheryDestroyed>()
+            .add_event::<SwarmlingDied>()
+            .add_event::<SpawnEffect>()
+            .init_resource::<GameRng>()
+            .init_resource::<InputBuffer>()
+            .init_resource::<Wave>()
+            .init_resource::<ArenaSource>()
+            .add_systems(Startup, (spawn_player, spawn_arena).after(load_balance))
+            .add_systems(
+                FixedUpdate,
+                (
+                    player_aim,
+                    buffer_player_input,
+                    player_move,
+                    player_dash,
+                    weapon_reload,
+                    player_attack,
+                    projectile_move,
+                    projectile_collision,
+                    swarmling_death,
+                    hatchery_damage,
+                    hatchery_death,
+                    hatchery_destruction,
+                    swarmling_spawn,
+                    swarmling_move,
+                    player_collision,
+                    player_take_damage,
+                    player_death,
+                )
+                    .chain(),
+            )
+            .insert_resource(Gravity(Vec2::ZERO));
+    }
+}
+
+/// Everything the player sees and touches on top of [`SwarmyGamePlugin`]: input devices,
+/// camera, menus, particles, decals, crosshair and HUD. Needs `DefaultPlugins`.
+pub struct SwarmyClientPlugin;
+
+impl Plugin for SwarmyClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            HanabiPlugin,
+            ParticleEffectsPlugin,
+            MenuPlugin,
+            ReplayPlugin,
+            CrosshairPlugin,
+            DecalPlugin,
+            HudPlugin,
+        ))
+        .add_event::<CameraEvent>()
+        .init_resource::<InputMap>()
+        .init_resource::<ActionState>()
+        .add_systems(Startup, (spawn_camera, load_input_map))
+        .add_systems(PreUpdate, update_action_state.after(InputSystem))
+        .add_systems(
+            Update,
+            (
+                (
+                    (movement_input, attack_input, aim_input)
+                        .run_if(not(replay_playing))
+                        .run_if(not(autopilot_enabled)),
+                    camera_input,
+                )
+                    .run_if(in_state(AppState::Start)),
+                camera_zoom,
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            camera_follow.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// A windowless app with just [`SwarmyGamePlugin`] and physics, seeded with `seed`.
+/// Every `update` advances time by exactly one fixed tick, so tests and batch runs can
+/// step the simulation deterministically.
+pub fn headless_app(seed: u64) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        StatesPlugin,
+        PhysicsPlugins::default(),
+    ))
+    // Gameplay code loads sprites and builds meshes, which only need the asset types here
+    .init_asset::<Image>()
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<TextureAtlasLayout>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ))
+    .insert_resource(GameRng::new(seed))
+    .add_plugins(SwarmyGamePlugin)
+    .insert_resource(RecordHighScores(false));
+    app
+}
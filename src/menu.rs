@@ -0,0 +1,424 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+use crate::consts::*;
+use crate::input::*;
+use crate::save::{LoadRun, SaveRun};
+use crate::score::*;
+
+const BUTTON_COLOR: Color = Color::srgb(0.2, 0.2, 0.25);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.3, 0.3, 0.4);
+const BUTTON_PRESSED_COLOR: Color = Color::srgb(0.4, 0.4, 0.6);
+
+/// Pause menu with the rebinding screen for the [`InputMap`], and the end-of-run summary.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RebindTarget>()
+            .add_systems(Update, toggle_pause)
+            .add_systems(
+                Update,
+                (
+                    rebind_capture.run_if(in_state(AppState::Paused)),
+                    menu_buttons,
+                    update_binding_labels.run_if(in_state(AppState::Paused)),
+                )
+                    .chain()
+                    .after(toggle_pause)
+                    .run_if(not(in_state(AppState::Start))),
+            )
+            .add_systems(OnEnter(AppState::Paused), (pause_time, spawn_pause_menu))
+            .add_systems(
+                OnExit(AppState::Paused),
+                (resume_time, despawn_pause_menu, save_input_map),
+            )
+            .add_systems(
+                OnEnter(AppState::Victory),
+                (pause_time, spawn_run_summary.after(finish_run)),
+            )
+            .add_systems(
+                OnEnter(AppState::GameOver),
+                (pause_time, spawn_run_summary.after(finish_run)),
+            );
+    }
+}
+
+/// The action waiting for its next binding on the rebinding screen.
+#[derive(Resource, Default)]
+pub struct RebindTarget(pub Option<InputAction>);
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component)]
+struct RunSummaryScreen;
+
+#[derive(Component)]
+struct BindingLabel(InputAction);
+
+#[derive(Component)]
+enum MenuButton {
+    Add(InputAction),
+    Clear(InputAction),
+    ResetDefaults,
+    SaveRun,
+    LoadRun,
+    Resume,
+    Quit,
+}
+
+fn toggle_pause(
+    action_state: Res<ActionState>,
+    rebind_target: Res<RebindTarget>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !action_state.just_pressed(InputAction::Pause) || rebind_target.0.is_some() {
+        return;
+    }
+    match state.get() {
+        AppState::Start => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::Start),
+        AppState::Victory | AppState::GameOver => {}
+    }
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_time(mut time: ResMut<Time<Virtual>>, mut rebind_target: ResMut<RebindTarget>) {
+    time.unpause();
+    rebind_target.0 = None;
+}
+
+fn save_input_map(input_map: Res<InputMap>) {
+    if let Err(err) = input_map.save(INPUT_MAP_PATH) {
+        warn!("Could not save input map to {}: {}", INPUT_MAP_PATH, err);
+    }
+}
+
+fn binding_text(input_map: &InputMap, rebind_target: &RebindTarget, action: InputAction) -> String {
+    if rebind_target.0 == Some(action) {
+        return String::from("Press a key, button or stick... (Esc to cancel)");
+    }
+    let bindings = input_map.get(action);
+    if bindings.is_empty() {
+        return String::from("-");
+    }
+    bindings
+        .iter()
+        .map(|binding| binding.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    rebind_target: Res<RebindTarget>,
+) {
+    commands
+        .spawn((
+            PauseMenu,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., 0.75)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Paused"),
+                TextFont {
+                    font_size: 48.,
+                    ..default()
+                },
+            ));
+            for action in InputAction::ALL {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(12.),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(action.label()),
+                            Node {
+                                width: Val::Px(160.),
+                                ..default()
+                            },
+                        ));
+                        row.spawn((
+                            BindingLabel(action),
+                            Text::new(binding_text(&input_map, &rebind_target, action)),
+                            Node {
+                                width: Val::Px(360.),
+                                ..default()
+                            },
+                        ));
+                        spawn_button(row, "Add", MenuButton::Add(action));
+                        spawn_button(row, "Clear", MenuButton::Clear(action));
+                    });
+            }
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(12.),
+                    margin: UiRect::top(Val::Px(16.)),
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_button(row, "Reset defaults", MenuButton::ResetDefaults);
+                    spawn_button(row, "Save run", MenuButton::SaveRun);
+                    spawn_button(row, "Load run", MenuButton::LoadRun);
+                    spawn_button(row, "Resume", MenuButton::Resume);
+                });
+        });
+}
+
+/// End-of-run summary with the high-score table, shown on victory or death.
+fn spawn_run_summary(
+    mut commands: Commands,
+    state: Res<State<AppState>>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    summary: Res<RunSummary>,
+) {
+    let title = if *state.get() == AppState::Victory {
+        "Level cleared"
+    } else {
+        "You died"
+    };
+    commands
+        .spawn((
+            RunSummaryScreen,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., 0.75)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 48.,
+                    ..default()
+                },
+            ));
+            parent.spawn(Text::new(format!(
+                "{} points   {} kills   {} hatcheries   best combo {}   {}",
+                score.points,
+                score.kills,
+                score.hatcheries,
+                score.best_combo,
+                format_duration(score.duration)
+            )));
+            if let Some(rank) = summary.rank {
+                parent.spawn(Text::new(format!("New high score: #{}", rank + 1)));
+            }
+            parent.spawn((
+                Text::new("High scores"),
+                TextFont {
+                    font_size: 24.,
+                    ..default()
+                },
+                Node {
+                    margin: UiRect::top(Val::Px(16.)),
+                    ..default()
+                },
+            ));
+            for (index, entry) in high_scores.entries.iter().enumerate() {
+                let color = if summary.rank == Some(index) {
+                    Color::srgb(1.0, 0.8, 0.2)
+                } else {
+                    Color::WHITE
+                };
+                parent.spawn((
+                    Text::new(format!(
+                        "{:>2}. {:>8}   {:>4} kills   {}   seed {}{}",
+                        index + 1,
+                        entry.points,
+                        entry.kills,
+                        format_duration(entry.duration),
+                        entry.seed,
+                        if entry.victory { "   cleared" } else { "" }
+                    )),
+                    TextColor(color),
+                ));
+            }
+            parent
+                .spawn(Node {
+                    margin: UiRect::top(Val::Px(16.)),
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_button(row, "Quit", MenuButton::Quit);
+                });
+        });
+}
+
+fn format_duration(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: &str, button: MenuButton) {
+    parent
+        .spawn((
+            button,
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(10.), Val::Px(4.)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn despawn_pause_menu(mut commands: Commands, q_menu: Query<Entity, With<PauseMenu>>) {
+    for entity in q_menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Binds the next key, mouse button, wheel direction or gamepad input to the [`RebindTarget`].
+fn rebind_capture(
+    mut rebind_target: ResMut<RebindTarget>,
+    mut input_map: ResMut<InputMap>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    mut mouse_wheel_event: EventReader<MouseWheel>,
+    q_gamepads: Query<&Gamepad>,
+) {
+    let wheel: f32 = mouse_wheel_event.read().map(|event| event.y).sum();
+    let Some(action) = rebind_target.0 else {
+        return;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebind_target.0 = None;
+        return;
+    }
+
+    let gamepad_button = q_gamepads
+        .iter()
+        .find_map(|gamepad| gamepad.get_just_pressed().next().copied());
+    let gamepad_axis = q_gamepads.iter().find_map(|gamepad| {
+        let stick = |axis_x, axis_y, value: Vec2| {
+            if value.x.abs() > value.y.abs() {
+                (axis_x, value.x)
+            } else {
+                (axis_y, value.y)
+            }
+        };
+        let left = stick_with_deadzone(gamepad.left_stick());
+        let right = stick_with_deadzone(gamepad.right_stick());
+        let (axis, value) = if left.length() > right.length() {
+            stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY, left)
+        } else {
+            stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY, right)
+        };
+        // Require a clear push so resting drift doesn't bind
+        (value.abs() > 0.5).then(|| {
+            let direction = if value > 0.0 {
+                AxisDirection::Positive
+            } else {
+                AxisDirection::Negative
+            };
+            Binding::GamepadAxis(axis, direction)
+        })
+    });
+
+    let binding = if let Some(key) = keyboard_input.get_just_pressed().next() {
+        Some(Binding::Key(*key))
+    } else if let Some(button) = mouse_button_input.get_just_pressed().next() {
+        Some(Binding::Mouse(*button))
+    } else if let Some(button) = gamepad_button {
+        Some(Binding::GamepadButton(button))
+    } else if let Some(binding) = gamepad_axis {
+        Some(binding)
+    } else if wheel > 0.0 {
+        Some(Binding::MouseWheel(WheelDirection::Up))
+    } else if wheel < 0.0 {
+        Some(Binding::MouseWheel(WheelDirection::Down))
+    } else {
+        None
+    };
+
+    if let Some(binding) = binding {
+        input_map.bind(action, binding);
+        rebind_target.0 = None;
+    }
+}
+
+fn menu_buttons(
+    mut q_buttons: Query<(&Interaction, &MenuButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut rebind_target: ResMut<RebindTarget>,
+    mut input_map: ResMut<InputMap>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+    mut save_run_event_writer: EventWriter<SaveRun>,
+    mut load_run_event_writer: EventWriter<LoadRun>,
+) {
+    for (interaction, button, mut background) in q_buttons.iter_mut() {
+        match interaction {
+            Interaction::Pressed => {
+                background.0 = BUTTON_PRESSED_COLOR;
+                match button {
+                    MenuButton::Add(action) => rebind_target.0 = Some(*action),
+                    MenuButton::Clear(action) => input_map.clear(*action),
+                    MenuButton::ResetDefaults => *input_map = InputMap::default(),
+                    MenuButton::SaveRun => {
+                        save_run_event_writer.send(SaveRun);
+                    }
+                    MenuButton::LoadRun => {
+                        load_run_event_writer.send(LoadRun);
+                    }
+                    MenuButton::Resume => next_state.set(AppState::Start),
+                    MenuButton::Quit => {
+                        app_exit_event_writer.send(AppExit::Success);
+                    }
+                }
+            }
+            Interaction::Hovered => background.0 = BUTTON_HOVER_COLOR,
+            Interaction::None => background.0 = BUTTON_COLOR,
+        }
+    }
+}
+
+fn update_binding_labels(
+    mut q_labels: Query<(&mut Text, &BindingLabel)>,
+    input_map: Res<InputMap>,
+    rebind_target: Res<RebindTarget>,
+) {
+    if !input_map.is_changed() && !rebind_target.is_changed() {
+        return;
+    }
+    for (mut text, label) in q_labels.iter_mut() {
+        text.0 = binding_text(&input_map, &rebind_target, label.0);
+    }
+}
@@ -1,173 +1,439 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_hanabi::prelude::*;
+use serde::Deserialize;
+
+use crate::projectile::{ProjectileFiredEvent, ProjectileHitEvent};
+use crate::swarm::{HatcheryDestroyed, SwarmSpawner};
+
+/// Effects loaded from `assets/effects/<name>.effect.ron` at startup.
+pub const EFFECT_NAMES: &[&str] = &[
+    "player_dash",
+    "swarmling_death",
+    "impact_spark",
+    "muzzle_flash",
+    "hatchery_chunk",
+    "hatchery_destruction",
+];
+/// Emitters per effect, i.e. how many bursts of the same effect can start in one frame.
+/// Later bursts in that frame are dropped, since the emitter they'd reuse has only just
+/// been restarted.
+pub const EFFECT_POOL_SIZE: usize = 16;
+
+/// Loads [`EffectDefinition`] files into the [`EffectLibrary`] and rebuilds the
+/// [`EffectAsset`] whenever a definition changes on disk.
+pub struct ParticleEffectsPlugin;
+
+impl Plugin for ParticleEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<EffectDefinition>()
+            .init_asset_loader::<EffectDefinitionLoader>()
+            .init_resource::<EffectLibrary>()
+            .init_resource::<EffectPool>()
+            .add_event::<SpawnEffect>()
+            .add_systems(Startup, (setup_particle_effects, setup_effect_pool).chain())
+            .add_systems(
+                Update,
+                (
+                    (projectile_hit_effects, projectile_fired_effects, hatchery_destroyed_effects),
+                    rebuild_particle_effects,
+                    spawn_effects,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A scalar that is either fixed or picked uniformly from a range per particle.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum EffectValue {
+    Fixed(f32),
+    Range(f32, f32),
+}
+
+impl EffectValue {
+    fn writer_expr(&self, writer: &ExprWriter) -> WriterExpr {
+        match *self {
+            EffectValue::Fixed(value) => writer.lit(value),
+            EffectValue::Range(min, max) => {
+                writer.lit(min) + writer.rand(ScalarType::Float) * writer.lit(max - min)
+            }
+        }
+    }
+
+    fn expr(&self, writer: &ExprWriter) -> ExprHandle {
+        self.writer_expr(writer).expr()
+    }
+
+    fn cpu_value(&self) -> CpuValue<f32> {
+        match *self {
+            EffectValue::Fixed(value) => value.into(),
+            EffectValue::Range(min, max) => CpuValue::Uniform((min, max)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum SpawnerDefinition {
+    Once(EffectValue),
+    Rate(EffectValue),
+    Burst { count: EffectValue, period: EffectValue },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum InitDefinition {
+    PositionCircle { radius: f32, volume: bool },
+    VelocityCircle { speed: EffectValue },
+    /// Velocity along [`SpawnEffect::rotation`], jittered by up to `spread`
+    VelocityDirectional { speed: EffectValue, spread: f32 },
+    Size(EffectValue),
+    Lifetime(EffectValue),
+}
+
+/// Data-driven description of a particle effect.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct EffectDefinition {
+    /// Maximum number of particles alive at a time
+    pub capacity: u32,
+    pub spawner: SpawnerDefinition,
+    pub init: Vec<InitDefinition>,
+    #[serde(default)]
+    pub acceleration: Vec2,
+    /// Color keys over the particle lifetime, from 0 (spawn) to 1 (death)
+    pub gradient: Vec<(f32, Vec4)>,
+    /// Size keys over the particle lifetime, empty to keep the initial size
+    #[serde(default)]
+    pub size_curve: Vec<(f32, Vec2)>,
+    /// Color the particles with the `color` property set by [`SpawnEffect::color`]
+    /// instead of the gradient
+    #[serde(default)]
+    pub tint: bool,
+}
+
+impl EffectDefinition {
+    pub fn build(&self, name: &str) -> EffectAsset {
+        let writer = ExprWriter::new();
+        let color = writer.add_property("color", Vec4::ONE.into());
+        let direction = writer.add_property("direction", Vec3::X.into());
+
+        let mut init_position = Vec::new();
+        let mut init_velocity = Vec::new();
+        let mut init_attributes = Vec::new();
+        for init in self.init.iter() {
+            match init {
+                InitDefinition::PositionCircle { radius, volume } => {
+                    init_position.push(SetPositionCircleModifier {
+                        center: writer.lit(Vec3::ZERO).expr(),
+                        axis: writer.lit(Vec3::Z).expr(),
+                        radius: writer.lit(*radius).expr(),
+                        dimension: if *volume {
+                            ShapeDimension::Volume
+                        } else {
+                            ShapeDimension::Surface
+                        },
+                    });
+                }
+                InitDefinition::VelocityCircle { speed } => {
+                    init_velocity.push(SetVelocityCircleModifier {
+                        center: writer.lit(Vec3::ZERO).expr(),
+                        axis: writer.lit(Vec3::Z).expr(),
+                        speed: speed.expr(&writer),
+                    });
+                }
+                InitDefinition::VelocityDirectional { speed, spread } => {
+                    let jitter = (writer.rand(VectorType::VEC3F) - writer.lit(Vec3::splat(0.5)))
+                        * writer.lit(Vec3::new(*spread * 2., *spread * 2., 0.));
+                    let velocity =
+                        (writer.prop(direction) + jitter).normalized() * speed.writer_expr(&writer);
+                    init_attributes.push(SetAttributeModifier::new(Attribute::VELOCITY, velocity.expr()));
+                }
+                InitDefinition::Size(size) => {
+                    init_attributes.push(SetAttributeModifier::new(Attribute::SIZE, size.expr(&writer)));
+                }
+                InitDefinition::Lifetime(lifetime) => {
+                    init_attributes.push(SetAttributeModifier::new(
+                        Attribute::LIFETIME,
+                        lifetime.expr(&writer),
+                    ));
+                }
+            }
+        }
+
+        let init_color = SetAttributeModifier::new(Attribute::HDR_COLOR, writer.prop(color).expr());
+
+        let update_accel = AccelModifier::new(writer.lit(self.acceleration.extend(0.)).expr());
+
+        let spawner = match &self.spawner {
+            SpawnerDefinition::Once(count) => Spawner::once(count.cpu_value(), false),
+            SpawnerDefinition::Rate(rate) => Spawner::rate(rate.cpu_value()),
+            SpawnerDefinition::Burst { count, period } => {
+                Spawner::burst(count.cpu_value(), period.cpu_value())
+            }
+        };
+
+        let mut gradient = Gradient::new();
+        for (ratio, color) in self.gradient.iter() {
+            gradient.add_key(*ratio, *color);
+        }
+
+        let mut effect = EffectAsset::new(self.capacity, spawner, writer.finish())
+            .with_name(name)
+            .update(update_accel);
+        effect = if self.tint {
+            effect.init(init_color)
+        } else {
+            effect.render(ColorOverLifetimeModifier { gradient })
+        };
+        for modifier in init_position {
+            effect = effect.init(modifier);
+        }
+        for modifier in init_velocity {
+            effect = effect.init(modifier);
+        }
+        for modifier in init_attributes {
+            effect = effect.init(modifier);
+        }
+        if !self.size_curve.is_empty() {
+            let mut size_gradient = Gradient::new();
+            for (ratio, size) in self.size_curve.iter() {
+                size_gradient.add_key(*ratio, *size);
+            }
+            effect = effect.render(SizeOverLifetimeModifier {
+                gradient: size_gradient,
+                screen_space_size: false,
+            });
+        }
+        effect
+    }
+}
+
+#[derive(Default)]
+pub struct EffectDefinitionLoader;
+
+impl AssetLoader for EffectDefinitionLoader {
+    type Asset = EffectDefinition;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effect.ron"]
+    }
+}
+
+struct LibraryEffect {
+    definition: Handle<EffectDefinition>,
+    effect: Handle<EffectAsset>,
+}
+
+/// Particle effects by name. The [`EffectAsset`] handles are stable, so entities keep
+/// their effect when the definition is reloaded.
+#[derive(Resource, Default)]
+pub struct EffectLibrary {
+    effects: HashMap<String, LibraryEffect>,
+}
+
+impl EffectLibrary {
+    pub fn get(&self, name: &str) -> Option<Handle<EffectAsset>> {
+        self.effects.get(name).map(|effect| effect.effect.clone())
+    }
+
+    /// Like [`EffectLibrary::get`], but falls back to an empty handle for unknown names.
+    pub fn handle(&self, name: &str) -> Handle<EffectAsset> {
+        self.get(name).unwrap_or_else(|| {
+            warn!("Unknown particle effect {}", name);
+            Handle::default()
+        })
+    }
+}
+
+/// Fires a pooled one-shot effect from the [`EffectLibrary`] at a world position.
+#[derive(Event, Clone, Debug)]
+pub struct SpawnEffect {
+    pub name: String,
+    pub position: Vec2,
+    /// Rotation around the z axis, in radians
+    pub rotation: f32,
+    /// Only used by effects with `tint: true`
+    pub color: Option<Color>,
+}
+
+impl SpawnEffect {
+    pub fn new(name: impl Into<String>, position: Vec2) -> Self {
+        SpawnEffect {
+            name: name.into(),
+            position,
+            rotation: 0.0,
+            color: None,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+/// Emitters that are moved to the requested position and restarted for every [`SpawnEffect`].
+/// Particles are simulated in world space, so reusing an emitter doesn't move live particles.
+#[derive(Resource, Default)]
+pub struct EffectPool {
+    emitters: HashMap<String, Vec<Entity>>,
+    next: HashMap<String, usize>,
+}
+
+impl EffectPool {
+    /// The least recently used emitter for `name`.
+    fn next_emitter(&mut self, name: &str) -> Option<Entity> {
+        let emitters = self.emitters.get(name)?;
+        let next = self.next.entry(String::from(name)).or_default();
+        let emitter = emitters[*next % emitters.len()];
+        *next = (*next + 1) % emitters.len();
+        Some(emitter)
+    }
+}
 
 pub fn setup_particle_effects(
-    mut effects: ResMut<Assets<EffectAsset>>,
-    mut commands: Commands,
+    mut library: ResMut<EffectLibrary>,
+    effects: Res<Assets<EffectAsset>>,
     assets: Res<AssetServer>,
 ) {
-    setup_player_dash_effect(&mut effects, &mut commands);
-    setup_swarmling_death_effect(&mut effects, &mut commands);
+    for name in EFFECT_NAMES {
+        library.effects.insert(
+            String::from(*name),
+            LibraryEffect {
+                definition: assets.load(format!("effects/{}.effect.ron", name)),
+                effect: effects.reserve_handle(),
+            },
+        );
+    }
+}
+
+fn setup_effect_pool(
+    mut commands: Commands,
+    mut pool: ResMut<EffectPool>,
+    library: Res<EffectLibrary>,
+) {
+    for name in EFFECT_NAMES {
+        let emitters = (0..EFFECT_POOL_SIZE)
+            .map(|_| {
+                commands
+                    .spawn((
+                        Name::new(format!("Effect emitter ({})", name)),
+                        ParticleEffectBundle::new(library.handle(name)),
+                        EffectProperties::default(),
+                    ))
+                    .id()
+            })
+            .collect();
+        pool.emitters.insert(String::from(*name), emitters);
+    }
 }
 
-#[derive(Resource)]
-pub struct PlayerDashEffect {
-    pub handle: Handle<EffectAsset>,
+fn spawn_effects(
+    mut spawn_effect_event_reader: EventReader<SpawnEffect>,
+    mut pool: ResMut<EffectPool>,
+    mut q_emitters: Query<(&mut Transform, &mut EffectInitializers, &mut EffectProperties)>,
+) {
+    let mut started: HashMap<&str, usize> = HashMap::default();
+    for event in spawn_effect_event_reader.read() {
+        let count = started.entry(event.name.as_str()).or_default();
+        if *count == EFFECT_POOL_SIZE {
+            warn_once!(
+                "More than {} bursts of particle effect {} in one frame, dropping the rest",
+                EFFECT_POOL_SIZE,
+                event.name
+            );
+            continue;
+        }
+        *count += 1;
+        let Some(emitter) = pool.next_emitter(&event.name) else {
+            warn!("No pooled emitters for particle effect {}", event.name);
+            continue;
+        };
+        if let Ok((mut transform, mut initializers, mut properties)) = q_emitters.get_mut(emitter) {
+            *transform = Transform::from_translation(event.position.extend(0.))
+                .with_rotation(Quat::from_rotation_z(event.rotation));
+            if let Some(color) = event.color {
+                properties.set("color", color.to_linear().to_vec4().into());
+            }
+            properties.set("direction", Vec2::from_angle(event.rotation).extend(0.).into());
+            initializers.reset();
+        }
+    }
 }
 
-fn setup_player_dash_effect(
-    mut effects: &mut ResMut<Assets<EffectAsset>>,
-    mut commands: &mut Commands,
+fn projectile_hit_effects(
+    mut projectile_hit_event_reader: EventReader<ProjectileHitEvent>,
+    mut spawn_effect_event_writer: EventWriter<SpawnEffect>,
+    q_hatchery: Query<(), With<SwarmSpawner>>,
 ) {
-    // Define a color gradient from red to transparent black
-    let mut gradient = Gradient::new();
-    gradient.add_key(0.0, Vec4::new(1., 0., 0., 1.));
-    gradient.add_key(1.0, Vec4::splat(0.));
-
-    //There are attributes, modifiers and properties
-    //attribute: quantity stored per particle for all particles
-    //properties: named variable stored per effect (cannot vary between particles)
-
-    let writer = ExprWriter::new();
-
-    // On spawn, randomly initialize the position of the particle
-    // to be over the surface of a sphere of radius 2 units.
-    let init_pos = SetPositionCircleModifier {
-        center: writer.lit(Vec3::ZERO).expr(),
-        axis: writer.lit(Vec3::Z).expr(),
-        radius: writer.lit(3.).expr(),
-        dimension: ShapeDimension::Volume,
-    };
-
-    // Initialize the particle size to a specific value
-    let init_size = SetAttributeModifier::new(
-        Attribute::SIZE,
-        writer.lit(2.).expr(), // Set the size to 2.0 (default is usually 1.0)
-    );
-
-    // Also initialize a radial initial velocity to 6 units/sec
-    // away from the (same) sphere center.
-    let init_vel = SetVelocityCircleModifier {
-        center: writer.lit(Vec3::ZERO).expr(),
-        axis: writer.lit(Vec3::Z).expr(),
-        speed: writer.lit(8.).expr(),
-    };
-
-    // Initialize the total lifetime of the particle, that is
-    // the time for which it's simulated and rendered. This modifier
-    // is almost always required, otherwise the particles won't show.
-    let lifetime = writer.lit(3.).expr(); // literal value "10.0"
-    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
-
-    // Every frame, add a gravity-like acceleration downward
-    let accel = writer.lit(Vec3::new(0., 0., 0.)).expr();
-    let update_accel = AccelModifier::new(accel);
-
-    // Create a new expression module from the writer
-    let module = writer.finish();
-
-    // Create the effect asset
-    let effect = EffectAsset::new(
-        // Maximum number of particles alive at a time
-        32768,
-        // Spawn at a rate of 5 particles per second
-        Spawner::once(5.0.into(), false),
-        // Move the expression module into the asset
-        module,
-    )
-    .with_name("DashParticleEffect")
-    .init(init_pos)
-    .init(init_vel)
-    .init(init_lifetime)
-    .init(init_size)
-    .update(update_accel)
-    // Render the particles with a color gradient over their
-    // lifetime. This maps the gradient key 0 to the particle spawn
-    // time, and the gradient key 1 to the particle death (10s).
-    .render(ColorOverLifetimeModifier { gradient });
-
-    // Insert into the asset system
-    let effect_handle = effects.add(effect);
-
-    commands.insert_resource(PlayerDashEffect {
-        handle: effect_handle,
-    });
-}
-
-#[derive(Resource)]
-pub struct SwarmlingDeathEffect {
-    pub handle: Handle<EffectAsset>,
-}
-
-fn setup_swarmling_death_effect(
-    mut effects: &mut ResMut<Assets<EffectAsset>>,
-    mut commands: &mut Commands
+    for hit in projectile_hit_event_reader.read() {
+        let rotation = hit.normal.to_angle();
+        spawn_effect_event_writer
+            .send(SpawnEffect::new("impact_spark", hit.position).with_rotation(rotation));
+        if q_hatchery.contains(hit.target) {
+            spawn_effect_event_writer
+                .send(SpawnEffect::new("hatchery_chunk", hit.position).with_rotation(rotation));
+        }
+    }
+}
+
+fn projectile_fired_effects(
+    mut projectile_fired_event_reader: EventReader<ProjectileFiredEvent>,
+    mut spawn_effect_event_writer: EventWriter<SpawnEffect>,
+) {
+    for fired in projectile_fired_event_reader.read() {
+        spawn_effect_event_writer.send(
+            SpawnEffect::new("muzzle_flash", fired.position).with_rotation(fired.direction.to_angle()),
+        );
+    }
+}
+
+fn hatchery_destroyed_effects(
+    mut hatchery_destroyed_event_reader: EventReader<HatcheryDestroyed>,
+    mut spawn_effect_event_writer: EventWriter<SpawnEffect>,
+) {
+    for destroyed in hatchery_destroyed_event_reader.read() {
+        spawn_effect_event_writer.send(SpawnEffect::new("hatchery_destruction", destroyed.position));
+    }
+}
+
+fn rebuild_particle_effects(
+    mut asset_event_reader: EventReader<AssetEvent<EffectDefinition>>,
+    definitions: Res<Assets<EffectDefinition>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    library: Res<EffectLibrary>,
 ) {
-    // Define a color gradient from red to transparent black
-    let mut gradient = Gradient::new();
-    gradient.add_key(0.0, Vec4::new(1., 0., 0., 1.));
-    gradient.add_key(1.0, Vec4::splat(0.));
-
-    let writer = ExprWriter::new();
-
-    let init_pos = SetPositionCircleModifier {
-        center: writer.lit(Vec3::ZERO).expr(),
-        axis: writer.lit(Vec3::Z).expr(),
-        radius: writer.lit(3.).expr(),
-        dimension: ShapeDimension::Volume,
-    };
-
-    // Initialize the particle size to a specific value
-    let init_size = SetAttributeModifier::new(
-        Attribute::SIZE,
-        writer.lit(2.).expr(), // Set the size to 2.0 (default is usually 1.0)
-    );
-
-    // Also initialize a radial initial velocity to 6 units/sec
-    // away from the (same) sphere center.
-    let init_vel = SetVelocityCircleModifier {
-        center: writer.lit(Vec3::ZERO).expr(),
-        axis: writer.lit(Vec3::Z).expr(),
-        speed: writer.lit(8.).expr(),
-    };
-
-    // Initialize the total lifetime of the particle, that is
-    // the time for which it's simulated and rendered. This modifier
-    // is almost always required, otherwise the particles won't show.
-    let lifetime = writer.lit(3.).expr(); // literal value "10.0"
-    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
-
-    // Every frame, add a gravity-like acceleration downward
-    let accel = writer.lit(Vec3::new(0., 0., 0.)).expr();
-    let update_accel = AccelModifier::new(accel);
-
-    // Create a new expression module from the writer
-    let module = writer.finish();
-
-    // Create the effect asset
-    let effect = EffectAsset::new(
-        // Maximum number of particles alive at a time
-        32768,
-        // Spawn at a rate of 5 particles per second
-        Spawner::once(15.0.into(), false),
-        // Move the expression module into the asset
-        module,
-    )
-    .with_name("SwarmlingDeathEffect")
-    .init(init_pos)
-    .init(init_vel)
-    .init(init_lifetime)
-    .init(init_size)
-    .update(update_accel)
-    // Render the particles with a color gradient over their
-    // lifetime. This maps the gradient key 0 to the particle spawn
-    // time, and the gradient key 1 to the particle death (10s).
-    .render(ColorOverLifetimeModifier { gradient });
-
-    // Insert into the asset system
-    let effect_handle = effects.add(effect);
-
-    commands.insert_resource(SwarmlingDeathEffect {
-        handle: effect_handle,
-    });
+    for event in asset_event_reader.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+        let Some(definition) = definitions.get(*id) else {
+            continue;
+        };
+        for (name, entry) in library.effects.iter() {
+            if entry.definition.id() == *id {
+                effects.insert(&entry.effect, definition.build(name));
+                info!("Built particle effect {}", name);
+            }
+        }
+    }
 }
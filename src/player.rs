@@ -1,34 +1,59 @@
 use avian2d::prelude::*;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
-use bevy_hanabi::prelude::*;
 
+use crate::balance::*;
 use crate::consts::*;
 use crate::input::*;
+use crate::particles::SpawnEffect;
 use crate::projectile::*;
 use crate::swarm::*;
+use crate::weapon::*;
 
 #[derive(Component, Reflect)]
-#[require(Sprite, Name(|| "Player"), Health, Collider(player_collider), RigidBody(player_rigidbody), Dash)]
+#[reflect(Component)]
+#[require(Sprite, Name(|| "Player"), Health, Collider(player_collider), RigidBody(player_rigidbody), Dash, Weapon)]
 pub struct Player {}
 
+/// Takes no damage. Toggled by the `god` console command.
+#[derive(Component, Default)]
+pub struct Invulnerable;
+
 #[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Dash {
     is_dashing: bool,
     direction: Vec2,
     duration: f32,
     elapsed: f32,
     cooldown: f32,
+    /// Length of the current cooldown, for [`Dash::cooldown_fraction`]
+    cooldown_duration: f32,
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        self.is_dashing
+    }
+
+    /// Fraction of the cooldown left, 0 when the dash is ready.
+    pub fn cooldown_fraction(&self) -> f32 {
+        if self.cooldown_duration <= 0.0 {
+            return 0.0;
+        }
+        (self.cooldown / self.cooldown_duration).clamp(0.0, 1.0)
+    }
 }
 
 impl Default for Dash {
     fn default() -> Self {
+        let balance = PlayerBalance::default();
         Dash {
             is_dashing: false,
             direction: Vec2::ZERO,
-            duration: PLAYER_DASH_DURATION,
+            duration: balance.dash_duration,
             elapsed: 0.0,
-            cooldown: PLAYER_DASH_COOLDOWN,
+            cooldown: balance.dash_cooldown,
+            cooldown_duration: balance.dash_cooldown,
         }
     }
 }
@@ -53,47 +78,55 @@ fn player_rigidbody() -> RigidBody {
 
 pub fn player_move(
     mut q_player: Query<(&mut LinearVelocity, &mut Dash), With<Player>>,
-    mut player_movement_event_reader: EventReader<PlayerMovementEvent>
+    mut player_movement_event_reader: EventReader<PlayerMovementEvent>,
+    mut input_buffer: ResMut<InputBuffer>,
+    balance: Res<GameBalance>,
 ) {
+    let Ok((mut linear_velocity, mut dash)) = q_player.get_single_mut() else {
+        return;
+    };
     for event in player_movement_event_reader.read() {
-        if let Ok((mut linear_velocity, mut dash)) = q_player.get_single_mut() {
-            if dash.is_dashing {
-                continue;
-            } else {
-                match event {
-                    PlayerMovementEvent::Move(direction) => {
-                        linear_velocity.0 = *direction * PLAYER_MOVEMENT_SPEED;
-                    }
-                    PlayerMovementEvent::Dash(direction) => {
-                        if dash.cooldown <= 0.0 {
-                            dash.direction = *direction;
-                            dash.is_dashing = true;
-                        }
-                    }
-                }
-            }
+        if dash.is_dashing {
+            continue;
+        }
+        // Dash presses are consumed from the input buffer below
+        if let PlayerMovementEvent::Move(direction) = event {
+            linear_velocity.0 = *direction * balance.player.movement_speed;
+        }
+    }
+    if !dash.is_dashing && dash.cooldown <= 0.0 {
+        if let Some(direction) = input_buffer.take_dash() {
+            dash.direction = direction;
+            dash.duration = balance.player.dash_duration;
+            dash.is_dashing = true;
         }
     }
 }
 
 pub fn player_dash(
-    mut q_player: Query<(&mut LinearVelocity, &mut Dash, &mut EffectInitializers), With<Player>>,
+    mut q_player: Query<(&mut LinearVelocity, &mut Dash, &Transform), With<Player>>,
+    mut spawn_effect_event_writer: EventWriter<SpawnEffect>,
+    balance: Res<GameBalance>,
     time: Res<Time>,
 ) {
-    for (mut linear_velocity, mut dash, mut e_initializers) in q_player.get_single_mut() {
+    for (mut linear_velocity, mut dash, transform) in q_player.get_single_mut() {
         if dash.is_dashing {
             dash.elapsed += time.delta_secs();
-            e_initializers.reset();
+            spawn_effect_event_writer.send(SpawnEffect::new(
+                "player_dash",
+                transform.translation.truncate(),
+            ));
             if dash.elapsed > dash.duration {
                 dash.is_dashing = false;
                 dash.direction = Vec2::ZERO;
                 dash.elapsed = 0.0;
-                dash.cooldown = PLAYER_DASH_COOLDOWN;
+                dash.cooldown = balance.player.dash_cooldown;
+                dash.cooldown_duration = balance.player.dash_cooldown;
                 continue;
             }
             let progress = dash.elapsed / dash.duration;
             let curve = EasingCurve::new(
-                dash.direction * PLAYER_DASH_SPEED,
+                dash.direction * balance.player.dash_speed,
                 Vec2::ZERO,
                 EaseFunction::SineOut,
             );
@@ -102,28 +135,21 @@ pub fn player_dash(
             linear_velocity.y = vel_2d.y;
         } else if dash.cooldown > 0.0 {
             dash.cooldown -= time.delta_secs();
-            dash.cooldown = dash.cooldown.clamp(0.0, PLAYER_DASH_COOLDOWN);
+            dash.cooldown = dash.cooldown.max(0.0);
         }
     }
 }
 
 pub fn player_aim(
     mut q_player: Query<&mut Transform, With<Player>>,
-    q_window: Query<&Window, With<PrimaryWindow>>,
-    q_camera: Query<(&Camera, &GlobalTransform)>,
+    mut player_aim_event_reader: EventReader<PlayerAimEvent>,
 ) {
-    if let Ok(window) = q_window.get_single() {
-        if let Some(cursor_position) = window.cursor_position() {
-            let (camera, camera_global_transform) = q_camera.single();
-            if let Ok(cursor_world_position) =
-                camera.viewport_to_world_2d(camera_global_transform, cursor_position)
-            {
-                if let Ok(mut player_transform) = q_player.get_single_mut() {
-                    // Calculate the angle between the player and the cursor
-                    let direction = cursor_world_position - player_transform.translation.truncate();
-                    let aim_angle = direction.y.atan2(direction.x);
-                    player_transform.rotation = Quat::from_rotation_z(aim_angle);
-                }
+    if let Some(PlayerAimEvent(direction)) = player_aim_event_reader.read().last() {
+        if let Ok(mut player_transform) = q_player.get_single_mut() {
+            if *direction != Vec2::ZERO {
+                // Calculate the angle of the aim direction
+                let aim_angle = direction.y.atan2(direction.x);
+                player_transform.rotation = Quat::from_rotation_z(aim_angle);
             }
         }
     }
@@ -132,31 +158,50 @@ pub fn player_aim(
 pub fn player_attack(
     mut commands: Commands,
     assets: Res<AssetServer>,
-    q_player: Query<&Transform, With<Player>>,
-    mut player_attack_event_reader: EventReader<PlayerAttackEvent>,
+    mut q_player: Query<(&Transform, &mut Weapon), With<Player>>,
+    mut input_buffer: ResMut<InputBuffer>,
+    mut rng: ResMut<GameRng>,
+    mut projectile_fired_event_writer: EventWriter<ProjectileFiredEvent>,
+    balance: Res<GameBalance>,
 ) {
-    for event in player_attack_event_reader.read() {
-        match event {
-            PlayerAttackEvent::PrimaryFire => {
-                if let Ok(player_transform) = q_player.get_single() {
-                    let forward = player_transform.rotation * Vec3::X;
-                    let spawn_offset = forward * 25.;
-                    let projectile_translation = player_transform.translation + spawn_offset;
-                    commands.spawn((
-                        Projectile::default(),
-                        Sprite {
-                            image: assets.load("sprites/projectiles/star_tiny.png"),
-                            ..default()
-                        },
-                        Transform {
-                            translation: projectile_translation,
-                            rotation: player_transform.rotation,
-                            ..default()
-                        },
-                    ));
-                }
-            }
+    // No weapon has a secondary fire yet, so those presses are just used up
+    while input_buffer
+        .take(|action| *action == BufferedAction::SecondaryFire)
+        .is_some()
+    {}
+    let Ok((player_transform, mut weapon)) = q_player.get_single_mut() else {
+        return;
+    };
+    // Presses while reloading stay buffered until they expire
+    while weapon.can_fire() {
+        if input_buffer
+            .take(|action| *action == BufferedAction::PrimaryFire)
+            .is_none()
+        {
+            break;
         }
+        weapon.fire();
+        let spread = (rng.f32() * 2.0 - 1.0) * weapon.spread;
+        let rotation = player_transform.rotation * Quat::from_rotation_z(spread);
+        let forward = player_transform.rotation * Vec3::X;
+        let spawn_offset = forward * 25.;
+        let projectile_translation = player_transform.translation + spawn_offset;
+        projectile_fired_event_writer.send(ProjectileFiredEvent {
+            position: projectile_translation.truncate(),
+            direction: (rotation * Vec3::X).truncate(),
+        });
+        commands.spawn((
+            Projectile::new(&balance.projectile),
+            Sprite {
+                image: assets.load("sprites/projectiles/star_tiny.png"),
+                ..default()
+            },
+            Transform {
+                translation: projectile_translation,
+                rotation,
+                ..default()
+            },
+        ));
     }
 }
 
@@ -164,19 +209,21 @@ pub fn player_collision(
     q_player: Query<&CollidingEntities, With<Player>>,
     mut q_swarmling: Query<Entity, With<Swarmling>>,
     mut player_damage_event_writer: EventWriter<PlayerDamageEvent>,
+    balance: Res<GameBalance>,
 ) {
     if let Ok(colliding_entities) = q_player.get_single() {
         for entity in colliding_entities.iter() {
             if let Ok(health) = q_swarmling.get_mut(*entity) {
-                player_damage_event_writer
-                    .send(PlayerDamageEvent::Collision(SWARMLING_COLLISION_DAMAGE));
+                player_damage_event_writer.send(PlayerDamageEvent::Collision(
+                    balance.swarmling.collision_damage,
+                ));
             }
         }
     }
 }
 
 pub fn player_take_damage(
-    mut q_player: Query<&mut Health, With<Player>>,
+    mut q_player: Query<&mut Health, (With<Player>, Without<Invulnerable>)>,
     mut player_damage_event_reader: EventReader<PlayerDamageEvent>,
     mut player_death_event_writer: EventWriter<PlayerDeathEvent>,
 ) {
@@ -195,8 +242,25 @@ pub fn player_take_damage(
     }
 }
 
-pub fn player_death(mut player_death_event_reader: EventReader<PlayerDeathEvent>) {
+pub fn player_death(
+    mut player_death_event_reader: EventReader<PlayerDeathEvent>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
     for _event in player_death_event_reader.read() {
         info!("PLAYER IS DEAD, YOU NOOB");
+        next_state.set(AppState::GameOver);
     }
 }
+
+pub fn spawn_player(mut commands: Commands, assets: Res<AssetServer>, balance: Res<GameBalance>) {
+    commands.spawn((
+        Player {},
+        Sprite {
+            image: assets.load("sprites/astronaut/astronaut.png"),
+            ..default()
+        },
+        Health(balance.player.health),
+        Weapon::new(&balance.weapon),
+        CollidingEntities::default(),
+    ));
+}
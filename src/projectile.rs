@@ -1,3 +1,4 @@
+use crate::balance::ProjectileBalance;
 use crate::consts::*;
 use crate::player::*;
 use avian2d::math::Scalar;
@@ -5,6 +6,7 @@ use avian2d::prelude::*;
 use bevy::prelude::*;
 
 #[derive(Component, Reflect)]
+#[reflect(Component)]
 #[require(Sprite, Name(|| "Projectile"), RigidBody(projectile_rigidbody), Collider(projectile_collider), CollidingEntities)]
 pub struct Projectile {
     displacement: Scalar,
@@ -13,17 +15,33 @@ pub struct Projectile {
     speed: Scalar,
 }
 
-impl Default for Projectile {
-    fn default() -> Projectile {
+impl Projectile {
+    pub fn new(balance: &ProjectileBalance) -> Projectile {
         Projectile {
             displacement: 0.0,
-            range: 750.0,
-            damage: 1.0,
-            speed: PROJECTILE_SPEED,
+            range: balance.range,
+            damage: balance.damage,
+            speed: balance.speed,
         }
     }
 }
 
+/// Sent when a projectile damages something with [`Health`].
+#[derive(Event)]
+pub struct ProjectileHitEvent {
+    pub target: Entity,
+    pub position: Vec2,
+    /// Surface normal at the hit, pointing away from the target
+    pub normal: Vec2,
+}
+
+/// Sent when a projectile is spawned by a weapon.
+#[derive(Event)]
+pub struct ProjectileFiredEvent {
+    pub position: Vec2,
+    pub direction: Vec2,
+}
+
 fn projectile_rigidbody() -> RigidBody {
     RigidBody::Dynamic
 }
@@ -49,14 +67,27 @@ pub fn projectile_move(
 
 pub fn projectile_collision(
     mut commands: Commands,
-    q_projectile: Query<(Entity, &Projectile, &CollidingEntities)>,
-    mut q_health: Query<&mut Health, (With<Collider>, Without<Player>)>,
+    q_projectile: Query<(Entity, &Projectile, &Transform, &CollidingEntities)>,
+    mut q_health: Query<(&mut Health, &GlobalTransform), (With<Collider>, Without<Player>)>,
+    mut projectile_hit_event_writer: EventWriter<ProjectileHitEvent>,
 ) {
-    for (projectile_entity, projectile, colliding_entities) in q_projectile.iter() {
+    for (projectile_entity, projectile, projectile_transform, colliding_entities) in q_projectile.iter() {
         if !colliding_entities.is_empty() {
             for colliding_entity in colliding_entities.iter() {
-                if let Ok(mut health) = q_health.get_mut(*colliding_entity) {
+                if let Ok((mut health, target_transform)) = q_health.get_mut(*colliding_entity) {
                     health.0 -= projectile.damage;
+                    let position = projectile_transform.translation.truncate();
+                    // Approximate the normal from the target's center, falling back to
+                    // the reverse of the projectile direction
+                    let backwards = -(projectile_transform.rotation * Vec3::X).truncate();
+                    let normal = (position - target_transform.translation().truncate())
+                        .try_normalize()
+                        .unwrap_or(backwards);
+                    projectile_hit_event_writer.send(ProjectileHitEvent {
+                        target: *colliding_entity,
+                        position,
+                        normal,
+                    });
                 }
                 commands.entity(projectile_entity).despawn();
             }
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::consts::*;
+use crate::input::*;
+use crate::player::player_aim;
+
+/// Records the per-tick player input to a replay file, or feeds a recorded one back
+/// into the [`FixedUpdate`] chain.
+///
+/// Start the game with `--record <file>` or `--replay <file>`.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayMode>()
+            .add_systems(PreStartup, start_replay)
+            .add_systems(
+                FixedUpdate,
+                (
+                    replay_playback.run_if(resource_exists::<ReplayPlayback>),
+                    replay_record.run_if(resource_exists::<ReplayRecorder>),
+                )
+                    .chain()
+                    .before(player_aim),
+            )
+            .add_systems(Last, save_replay.run_if(resource_exists::<ReplayRecorder>));
+    }
+}
+
+#[derive(Resource, Default, Clone, Debug)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Playback(PathBuf),
+}
+
+impl ReplayMode {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut mode = ReplayMode::Off;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => {
+                    if let Some(path) = args.next() {
+                        mode = ReplayMode::Record(path.into());
+                    }
+                }
+                "--replay" => {
+                    if let Some(path) = args.next() {
+                        mode = ReplayMode::Playback(path.into());
+                    }
+                }
+                _ => {}
+            }
+        }
+        mode
+    }
+}
+
+/// Run condition for the live input systems, which are replaced by the replay.
+pub fn replay_playing(mode: Res<ReplayMode>) -> bool {
+    matches!(*mode, ReplayMode::Playback(_))
+}
+
+/// All player input that reached the [`FixedUpdate`] chain during one tick.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayTick {
+    pub movement: Vec<PlayerMovementEvent>,
+    pub attack: Vec<PlayerAttackEvent>,
+    pub aim: Option<Vec2>,
+}
+
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub ticks: Vec<ReplayTick>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let contents = ron::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    replay: Replay,
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    tick: usize,
+}
+
+/// Seeds the [`GameRng`] before anything is spawned, from the replay when playing one back.
+fn start_replay(mut commands: Commands, mode: Res<ReplayMode>) {
+    let mut rng = GameRng::default();
+    match mode.clone() {
+        ReplayMode::Off => {}
+        ReplayMode::Record(path) => {
+            info!("Recording replay to {} with seed {}", path.display(), rng.seed);
+            commands.insert_resource(ReplayRecorder {
+                path,
+                replay: Replay {
+                    seed: rng.seed,
+                    ticks: Vec::new(),
+                },
+            });
+        }
+        ReplayMode::Playback(path) => match Replay::load(&path) {
+            Ok(replay) => {
+                info!("Playing replay {} with seed {}", path.display(), replay.seed);
+                rng = GameRng::new(replay.seed);
+                commands.insert_resource(ReplayPlayback { replay, tick: 0 });
+            }
+            Err(err) => {
+                error!("Could not load replay {}: {}", path.display(), err);
+                commands.insert_resource(ReplayMode::Off);
+            }
+        },
+    }
+    commands.insert_resource(rng);
+}
+
+fn replay_record(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut movement_event_reader: EventReader<PlayerMovementEvent>,
+    mut attack_event_reader: EventReader<PlayerAttackEvent>,
+    mut aim_event_reader: EventReader<PlayerAimEvent>,
+) {
+    recorder.replay.ticks.push(ReplayTick {
+        movement: movement_event_reader.read().cloned().collect(),
+        attack: attack_event_reader.read().cloned().collect(),
+        aim: aim_event_reader.read().last().map(|event| event.0),
+    });
+}
+
+fn replay_playback(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    mut movement_event_writer: EventWriter<PlayerMovementEvent>,
+    mut attack_event_writer: EventWriter<PlayerAttackEvent>,
+    mut aim_event_writer: EventWriter<PlayerAimEvent>,
+) {
+    let Some(tick) = playback.replay.ticks.get(playback.tick).cloned() else {
+        info!("Replay finished after {} ticks", playback.tick);
+        commands.remove_resource::<ReplayPlayback>();
+        return;
+    };
+    movement_event_writer.send_batch(tick.movement);
+    attack_event_writer.send_batch(tick.attack);
+    if let Some(direction) = tick.aim {
+        aim_event_writer.send(PlayerAimEvent(direction));
+    }
+    playback.tick += 1;
+}
+
+fn save_replay(mut app_exit_event_reader: EventReader<AppExit>, recorder: Res<ReplayRecorder>) {
+    if app_exit_event_reader.read().next().is_some() {
+        match recorder.replay.save(&recorder.path) {
+            Ok(()) => info!(
+                "Saved replay with {} ticks to {}",
+                recorder.replay.ticks.len(),
+                recorder.path.display()
+            ),
+            Err(err) => error!("Could not save replay to {}: {}", recorder.path.display(), err),
+        }
+    }
+}
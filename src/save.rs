@@ -0,0 +1,307 @@
+use avian2d::prelude::*;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::event::EventCursor;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::reflect::{PartialReflect, TypeRegistration, TypeRegistry};
+use bevy::scene::serde::SceneDeserializer;
+use serde::de::DeserializeSeed;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::arena::*;
+use crate::consts::*;
+use crate::decals::{Decal, DecalBudget};
+use crate::input::InputBuffer;
+use crate::player::{Dash, Player};
+use crate::projectile::Projectile;
+use crate::replay::ReplayMode;
+use crate::score::{Points, Score};
+use crate::swarm::*;
+use crate::weapon::Weapon;
+
+/// Saves the run in progress to a [`DynamicScene`] and restores it.
+///
+/// Only gameplay components are written. State that can't be reflected is rebuilt on load:
+/// sprites and colliders from the entity's marker component, [`SwarmlingPath`] noise from
+/// [`Swarmling::seed`], and the terrain from the arena seed. Particle effects live on the
+/// pooled emitters, which are never part of the snapshot.
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<(Dash, Dying, Swarmling, SavedRun)>()
+            .add_event::<SaveRun>()
+            .add_event::<LoadRun>()
+            .add_event::<RunLoaded>()
+            .add_systems(Update, (save_run, load_run).chain());
+    }
+}
+
+#[derive(Event)]
+pub struct SaveRun;
+
+#[derive(Event)]
+pub struct LoadRun;
+
+/// Sent once a saved run has replaced the one in progress.
+#[derive(Event)]
+pub struct RunLoaded;
+
+/// Run state that lives outside of components, stored as a scene resource.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct SavedRun {
+    pub seed: u64,
+    pub rng_state: u64,
+}
+
+/// `savegame.scn.ron` in the user's data directory, if the platform has one.
+pub fn save_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("swarmy").join("savegame.scn.ron"))
+}
+
+type RunEntityFilter = Or<(
+    With<Player>,
+    With<Swarmling>,
+    With<Projectile>,
+    With<Hatchery>,
+)>;
+
+fn save_run(world: &mut World, mut save_run_events: Local<EventCursor<SaveRun>>) {
+    if save_run_events.read(world.resource::<Events<SaveRun>>()).count() == 0 {
+        return;
+    }
+    let Some(path) = save_path() else {
+        warn!("No data directory to save the run to");
+        return;
+    };
+
+    let rng = world.resource::<GameRng>();
+    let saved_run = SavedRun {
+        seed: rng.seed,
+        rng_state: rng.state(),
+    };
+    world.insert_resource(saved_run);
+
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, RunEntityFilter>()
+        .iter(world)
+        .collect();
+    let scene = DynamicSceneBuilder::from_world(world)
+        .deny_all()
+        .allow::<Name>()
+        .allow::<Transform>()
+        .allow::<LinearVelocity>()
+        .allow::<Health>()
+        .allow::<Points>()
+        .allow::<Dying>()
+        .allow::<Player>()
+        .allow::<Dash>()
+        .allow::<Weapon>()
+        .allow::<Projectile>()
+        .allow::<Swarmling>()
+        .allow::<Hatchery>()
+        .allow::<SwarmSpawner>()
+        .deny_all_resources()
+        .allow_resource::<Score>()
+        .allow_resource::<Wave>()
+        .allow_resource::<SavedRun>()
+        .extract_entities(entities.into_iter())
+        .extract_resources()
+        .build();
+    world.remove_resource::<SavedRun>();
+
+    let serialized = {
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+        scene.serialize(&type_registry)
+    };
+    let result = serialized.map_err(|e| e.to_string()).and_then(|contents| {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, contents).map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(()) => info!("Saved run to {}", path.display()),
+        Err(err) => warn!("Could not save run to {}: {}", path.display(), err),
+    }
+}
+
+fn load_run(world: &mut World, mut load_run_events: Local<EventCursor<LoadRun>>) {
+    if load_run_events.read(world.resource::<Events<LoadRun>>()).count() == 0 {
+        return;
+    }
+    if world
+        .get_resource::<ReplayMode>()
+        .is_some_and(|mode| !matches!(mode, ReplayMode::Off))
+    {
+        warn!("Loading a run would break the replay, ignoring");
+        return;
+    }
+    let Some(path) = save_path() else {
+        warn!("No data directory to load the run from");
+        return;
+    };
+
+    let scene = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| {
+            let type_registry = world.resource::<AppTypeRegistry>().read();
+            let mut deserializer =
+                ron::de::Deserializer::from_str(&contents).map_err(|e| e.to_string())?;
+            SceneDeserializer {
+                type_registry: &type_registry,
+            }
+            .deserialize(&mut deserializer)
+            .map_err(|e| e.to_string())
+        });
+    let scene = scene.and_then(|scene| {
+        validate_scene(&scene, &world.resource::<AppTypeRegistry>().read()).map(|()| scene)
+    });
+    let scene = match scene {
+        Ok(scene) => scene,
+        Err(err) => {
+            warn!("Could not load run from {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    // Clear out the current run before writing the saved one over it. The scene has been
+    // validated, so writing it can't fail part way and leave no run at all.
+    let current: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(RunEntityFilter, With<Decal>)>>()
+        .iter(world)
+        .collect();
+    for entity in current {
+        world.entity_mut(entity).despawn_recursive();
+    }
+    *world.resource_mut::<DecalBudget>() = DecalBudget::default();
+    *world.resource_mut::<InputBuffer>() = InputBuffer::default();
+
+    let mut entity_map = EntityHashMap::default();
+    if let Err(err) = scene.write_to_world(world, &mut entity_map) {
+        warn!("Could not load run from {}: {}", path.display(), err);
+        return;
+    }
+
+    if let Some(saved_run) = world.remove_resource::<SavedRun>() {
+        let previous_seed = world.resource::<GameRng>().seed;
+        world.insert_resource(GameRng::restore(saved_run.seed, saved_run.rng_state));
+        if saved_run.seed != previous_seed
+            && matches!(world.resource::<ArenaSource>(), ArenaSource::Generated)
+        {
+            if let Err(err) = world.run_system_once_with(saved_run.seed, rebuild_terrain) {
+                warn!("Could not rebuild the arena: {}", err);
+            }
+        }
+    }
+
+    let loaded: Vec<Entity> = entity_map.values().copied().collect();
+    if let Err(err) = world.run_system_once_with(loaded, restore_loaded_entities) {
+        warn!("Could not restore loaded entities: {}", err);
+    }
+    world.send_event(RunLoaded);
+    info!("Loaded run from {}", path.display());
+}
+
+/// Checks what [`DynamicScene::write_to_world`] would reject, so a bad save is caught
+/// before the current run is torn down.
+fn validate_scene(scene: &DynamicScene, type_registry: &TypeRegistry) -> Result<(), String> {
+    let components = scene
+        .entities
+        .iter()
+        .flat_map(|entity| entity.components.iter());
+    for component in components {
+        let registration = registration(component.as_ref(), type_registry)?;
+        if registration.data::<ReflectComponent>().is_none() {
+            return Err(format!(
+                "{} is not a reflected component",
+                registration.type_info().type_path()
+            ));
+        }
+    }
+    for resource in scene.resources.iter() {
+        let registration = registration(resource.as_ref(), type_registry)?;
+        if registration.data::<ReflectResource>().is_none() {
+            return Err(format!(
+                "{} is not a reflected resource",
+                registration.type_info().type_path()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn registration<'a>(
+    value: &dyn PartialReflect,
+    type_registry: &'a TypeRegistry,
+) -> Result<&'a TypeRegistration, String> {
+    let type_info = value
+        .get_represented_type_info()
+        .ok_or_else(|| format!("{} has no represented type", value.reflect_type_path()))?;
+    type_registry
+        .get(type_info.type_id())
+        .ok_or_else(|| format!("{} is not registered", type_info.type_path()))
+}
+
+fn rebuild_terrain(
+    In(seed): In<u64>,
+    mut commands: Commands,
+    q_terrain: Query<Entity, Or<(With<ArenaGround>, With<Rock>)>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for entity in q_terrain.iter() {
+        commands.entity(entity).despawn();
+    }
+    spawn_terrain(&mut commands, &generate_arena(seed), &mut meshes, &mut materials);
+}
+
+/// Puts back what the scene doesn't carry: sprites, colliders, contacts and path noise.
+fn restore_loaded_entities(
+    In(loaded): In<Vec<Entity>>,
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    q_player: Query<(), With<Player>>,
+    q_swarmling: Query<&Swarmling>,
+    q_projectile: Query<(), With<Projectile>>,
+    q_hatchery: Query<(&Hatchery, Option<&Dying>)>,
+) {
+    for entity in loaded {
+        let mut entity_commands = commands.entity(entity);
+        if q_player.contains(entity) {
+            entity_commands.insert((
+                Sprite {
+                    image: assets.load("sprites/astronaut/astronaut.png"),
+                    ..default()
+                },
+                CollidingEntities::default(),
+            ));
+        } else if let Ok(swarmling) = q_swarmling.get(entity) {
+            entity_commands.insert((
+                SwarmlingPath::new(swarmling.seed),
+                Sprite {
+                    image: assets.load("sprites/bug/bug.png"),
+                    ..default()
+                },
+            ));
+        } else if q_projectile.contains(entity) {
+            entity_commands.insert(Sprite {
+                image: assets.load("sprites/projectiles/star_tiny.png"),
+                ..default()
+            });
+        } else if let Ok((hatchery, dying)) = q_hatchery.get(entity) {
+            let alpha = dying.map_or(1., |dying| 1. - dying.timer.fraction());
+            entity_commands.insert(Sprite {
+                image: assets.load("sprites/hatchery/hatchery.png"),
+                color: hatchery.stage.tint().with_alpha(alpha),
+                ..default()
+            });
+            // Dying hatcheries have already lost their body
+            if dying.is_none() {
+                entity_commands.insert((RigidBody::Static, Collider::rectangle(84., 84.)));
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::balance::{GameBalance, ScoreBalance};
+use crate::consts::*;
+use crate::player::{player_death, PlayerDamageEvent};
+use crate::swarm::{HatcheryDestroyed, SwarmlingDied};
+
+/// Points for kills with a combo multiplier, and the persisted high-score table.
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .init_resource::<HighScores>()
+            .init_resource::<RecordHighScores>()
+            .register_type::<(Points, Score)>()
+            .add_systems(Startup, load_high_scores.run_if(recording_high_scores))
+            .add_systems(
+                FixedUpdate,
+                (score_kills, score_combo).chain().after(player_death),
+            )
+            .add_systems(OnEnter(AppState::Victory), finish_run)
+            .add_systems(OnEnter(AppState::GameOver), finish_run);
+    }
+}
+
+/// Score awarded for killing this entity, before the combo multiplier.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Points(pub u32);
+
+/// The current run's score and stats.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct Score {
+    pub points: u64,
+    pub kills: u32,
+    pub hatcheries: u32,
+    /// Kills in the current combo
+    pub combo: u32,
+    pub best_combo: u32,
+    /// Seconds of play, not counting pauses
+    pub duration: f32,
+    combo_timer: Timer,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Score {
+            points: 0,
+            kills: 0,
+            hatcheries: 0,
+            combo: 0,
+            best_combo: 0,
+            duration: 0.,
+            combo_timer: Timer::from_seconds(ScoreBalance::default().combo_window, TimerMode::Once),
+        }
+    }
+}
+
+impl Score {
+    pub fn multiplier(&self, balance: &ScoreBalance) -> u32 {
+        (1 + self.combo / balance.combo_step.max(1)).min(balance.max_multiplier)
+    }
+
+    /// Extends the combo and adds `points` at the new multiplier.
+    pub fn add_kill(&mut self, points: u32, balance: &ScoreBalance) {
+        self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
+        self.combo_timer.set_duration(Duration::from_secs_f32(balance.combo_window));
+        self.combo_timer.reset();
+        self.points += (points * self.multiplier(balance)) as u64;
+    }
+
+    pub fn break_combo(&mut self) {
+        self.combo = 0;
+    }
+
+    /// Fraction of the combo window left before the combo breaks.
+    pub fn combo_remaining(&self) -> f32 {
+        if self.combo == 0 {
+            0.
+        } else {
+            self.combo_timer.fraction_remaining()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighScoreEntry {
+    pub points: u64,
+    pub seed: u64,
+    pub duration: f32,
+    pub kills: u32,
+    pub hatcheries: u32,
+    pub victory: bool,
+}
+
+/// The best [`MAX_HIGH_SCORES`] runs, highest first.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    /// `highscores.ron` in the user's data directory, if the platform has one.
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("swarmy").join("highscores.ron"))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Adds `entry` to the table, returning its 0-based rank if it made the cut.
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| entry.points > other.points)
+            .unwrap_or(self.entries.len());
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+}
+
+/// Whether finished runs go into the [`HighScores`] file. Headless runs turn this off so
+/// tests and batch simulations never touch the player's table.
+#[derive(Resource)]
+pub struct RecordHighScores(pub bool);
+
+impl Default for RecordHighScores {
+    fn default() -> Self {
+        RecordHighScores(true)
+    }
+}
+
+fn recording_high_scores(record: Res<RecordHighScores>) -> bool {
+    record.0
+}
+
+/// Where the finished run placed in the [`HighScores`], for the end-of-run summary.
+#[derive(Resource, Debug)]
+pub struct RunSummary {
+    pub rank: Option<usize>,
+}
+
+fn load_high_scores(mut high_scores: ResMut<HighScores>) {
+    let Some(path) = HighScores::path() else {
+        return;
+    };
+    match HighScores::load(&path) {
+        Ok(loaded) => *high_scores = loaded,
+        Err(_) if !path.exists() => {}
+        Err(err) => warn!("Could not load high scores from {}: {}", path.display(), err),
+    }
+}
+
+fn score_kills(
+    mut score: ResMut<Score>,
+    mut swarmling_died_event_reader: EventReader<SwarmlingDied>,
+    mut hatchery_destroyed_event_reader: EventReader<HatcheryDestroyed>,
+    balance: Res<GameBalance>,
+) {
+    for died in swarmling_died_event_reader.read() {
+        score.kills += 1;
+        score.add_kill(died.points, &balance.score);
+    }
+    for destroyed in hatchery_destroyed_event_reader.read() {
+        score.hatcheries += 1;
+        score.add_kill(destroyed.points, &balance.score);
+    }
+}
+
+/// Runs the clock and breaks the combo when its window runs out or the player is hurt.
+fn score_combo(
+    mut score: ResMut<Score>,
+    mut player_damage_event_reader: EventReader<PlayerDamageEvent>,
+    time: Res<Time>,
+) {
+    score.duration += time.delta_secs();
+    score.combo_timer.tick(time.delta());
+    if score.combo_timer.just_finished() || player_damage_event_reader.read().next().is_some() {
+        score.break_combo();
+    }
+}
+
+pub fn finish_run(
+    mut commands: Commands,
+    state: Res<State<AppState>>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+    record: Res<RecordHighScores>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    info!(
+        "Run over: {} points, {} kills, {} hatcheries in {:.0}s",
+        score.points, score.kills, score.hatcheries, score.duration
+    );
+    if !record.0 {
+        commands.insert_resource(RunSummary { rank: None });
+        return;
+    }
+    let rank = high_scores.insert(HighScoreEntry {
+        points: score.points,
+        seed: rng.seed,
+        duration: score.duration,
+        kills: score.kills,
+        hatcheries: score.hatcheries,
+        victory: *state.get() == AppState::Victory,
+    });
+    if rank.is_some() {
+        if let Some(path) = HighScores::path() {
+            if let Err(err) = high_scores.save(&path) {
+                warn!("Could not save high scores to {}: {}", path.display(), err);
+            }
+        }
+    }
+    commands.insert_resource(RunSummary { rank });
+}
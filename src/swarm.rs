@@ -1,11 +1,13 @@
+use crate::balance::*;
 use crate::consts::*;
 use crate::particles::*;
 use crate::player::*;
+use crate::score::Points;
 use avian2d::math::Vector;
 use avian2d::prelude::*;
 use bevy::prelude::*;
-use bevy_hanabi::{EffectInitializers, ParticleEffect, ParticleEffectBundle};
 use fastnoise_lite::{FastNoiseLite, NoiseType};
+use std::time::Duration;
 
 #[derive(Resource)]
 pub struct SwarmNoise {
@@ -20,28 +22,47 @@ impl Default for SwarmNoise {
     }
 }
 
-#[derive(Component)]
-#[require(Sprite, Name(|| "Swarmling"), Health, Collider(swarmling_collider), RigidBody(swarmling_rigidbody))]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+#[require(Sprite, Name(|| "Swarmling"), Health, Points(swarmling_points), Collider(swarmling_collider), RigidBody(swarmling_rigidbody))]
 pub struct Swarmling {
     state: BehaviorState,
-    path_noise: FastNoiseLite,
+    /// Seed of the [`SwarmlingPath`] noise, so it can be rebuilt after loading a save
+    pub seed: i32,
 }
 
-impl Default for Swarmling {
-    fn default() -> Self {
-        let mut noise = FastNoiseLite::new();
-        noise.set_noise_type(Some(NoiseType::Perlin));
-        noise.set_frequency(Some(0.2));
-        noise.seed = fastrand::i32(0..100000);
+impl Swarmling {
+    pub fn new(seed: i32) -> Self {
         Swarmling {
             state: BehaviorState::default(),
-            path_noise: noise,
+            seed,
         }
     }
+
+    pub(crate) fn state(&self) -> BehaviorState {
+        self.state
+    }
+}
+
+/// Noise driving a swarmling's wander path. Not reflected, so saves rebuild it from
+/// [`Swarmling::seed`].
+#[derive(Component)]
+pub struct SwarmlingPath {
+    noise: FastNoiseLite,
+}
+
+impl SwarmlingPath {
+    pub fn new(seed: i32) -> Self {
+        let mut noise = FastNoiseLite::new();
+        noise.set_noise_type(Some(NoiseType::Perlin));
+        noise.set_frequency(Some(0.2));
+        noise.seed = seed;
+        SwarmlingPath { noise }
+    }
 }
 
-#[derive(Reflect, Default)]
-enum BehaviorState {
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum BehaviorState {
     #[default]
     Wander,
     Flee,
@@ -49,10 +70,134 @@ enum BehaviorState {
 }
 
 #[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct SwarmSpawner {
     pub spawn_timer: Timer,
 }
 
+/// How badly a hatchery is hurt, by fraction of its starting health.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HatcheryStage {
+    #[default]
+    Intact,
+    Damaged,
+    /// Below half health, spawning in a panic
+    Critical,
+}
+
+impl HatcheryStage {
+    pub fn from_fraction(fraction: f32, balance: &HatcheryBalance) -> Self {
+        if fraction >= balance.damaged_fraction {
+            HatcheryStage::Intact
+        } else if fraction >= balance.critical_fraction {
+            HatcheryStage::Damaged
+        } else {
+            HatcheryStage::Critical
+        }
+    }
+
+    pub fn tint(&self) -> Color {
+        match self {
+            HatcheryStage::Intact => Color::WHITE,
+            HatcheryStage::Damaged => Color::srgb(1.0, 0.75, 0.6),
+            HatcheryStage::Critical => Color::srgb(1.0, 0.4, 0.35),
+        }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Hatchery {
+    pub max_health: f32,
+    pub spawn_interval: f32,
+    pub stage: HatcheryStage,
+}
+
+/// The current wave, starting at 1. Each destroyed hatchery starts the next one.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct Wave(pub u32);
+
+impl Default for Wave {
+    fn default() -> Self {
+        Wave(1)
+    }
+}
+
+/// Sent when a swarmling is killed, before it is despawned.
+#[derive(Event)]
+pub struct SwarmlingDied {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub points: u32,
+}
+
+/// Sent once when a hatchery's [`Health`] runs out, as its destruction sequence starts.
+#[derive(Event)]
+pub struct HatcheryDestroyed {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub points: u32,
+    /// Hatcheries still standing after this one
+    pub remaining: usize,
+}
+
+/// Per-hatchery overrides from level files. Unset values come from the [`HatcheryBalance`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HatcherySettings {
+    pub spawn_interval: Option<f32>,
+    pub health: Option<f32>,
+}
+
+/// A hatchery that spawns swarmlings at `position`.
+pub fn hatchery_bundle(
+    assets: &AssetServer,
+    position: Vec2,
+    settings: HatcherySettings,
+    balance: &HatcheryBalance,
+) -> impl Bundle {
+    let spawn_interval = settings.spawn_interval.unwrap_or(balance.spawn_interval);
+    let health = settings.health.unwrap_or(balance.health);
+    (
+        Hatchery {
+            max_health: health,
+            spawn_interval,
+            stage: HatcheryStage::Intact,
+        },
+        SwarmSpawner {
+            spawn_timer: Timer::from_seconds(spawn_interval, TimerMode::Repeating),
+        },
+        Name::new("Hatchery"),
+        Sprite {
+            image: assets.load("sprites/hatchery/hatchery.png"),
+            ..default()
+        },
+        Transform::from_translation(position.extend(0.)),
+        RigidBody::Static,
+        Collider::rectangle(84., 84.),
+        Health(health),
+        Points(balance.points),
+    )
+}
+
+/// A swarmling whose wander path is driven by `seed`, without a position.
+pub fn swarmling_bundle(assets: &AssetServer, seed: i32, balance: &SwarmlingBalance) -> impl Bundle {
+    (
+        Swarmling::new(seed),
+        SwarmlingPath::new(seed),
+        Health(balance.health),
+        Points(balance.points),
+        Sprite {
+            image: assets.load("sprites/bug/bug.png"),
+            ..default()
+        },
+    )
+}
+
+fn swarmling_points() -> Points {
+    Points(SwarmlingBalance::default().points)
+}
+
 fn swarmling_collider() -> Collider {
     Collider::circle(5.)
 }
@@ -63,28 +208,22 @@ fn swarmling_rigidbody() -> RigidBody {
 
 pub fn swarmling_spawn(
     mut commands: Commands,
-    mut q_swarm_spawner: Query<(&mut SwarmSpawner, &Transform)>,
-    swarmling_death_effect: Res<SwarmlingDeathEffect>,
+    mut q_swarm_spawner: Query<(&mut SwarmSpawner, &Transform), Without<Dying>>,
     assets: Res<AssetServer>,
     time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    balance: Res<GameBalance>,
 ) {
     for (mut spawner, transform) in q_swarm_spawner.iter_mut() {
         spawner.spawn_timer.tick(time.delta());
         if spawner.spawn_timer.just_finished() {
             let mut swarmling_transform = transform.clone();
             swarmling_transform.translation.y += 55.;
+            let seed = rng.i32(0..100000);
             commands.spawn((
-                Swarmling::default(),
-                Sprite {
-                    image: assets.load("sprites/bug/bug.png"),
-                    ..default()
-                },
-                ExternalImpulse::new(Vector::new(0., SWARMLING_LAUNCH_SPEED)),
-                ParticleEffectBundle {
-                    effect: ParticleEffect::new(swarmling_death_effect.handle.clone()),
-                    transform: swarmling_transform, // Use your transform here
-                    ..default()
-                },
+                swarmling_bundle(&assets, seed, &balance.swarmling),
+                ExternalImpulse::new(Vector::new(0., balance.swarmling.launch_speed)),
+                swarmling_transform,
             ));
         }
     }
@@ -95,24 +234,26 @@ pub fn swarmling_move(
         (
             &mut ExternalImpulse,
             &Swarmling,
+            &SwarmlingPath,
         ),
         Without<Player>,
     >,
     q_player: Query<&Transform, (With<Player>, Without<Swarmling>)>,
+    balance: Res<GameBalance>,
     time: Res<Time>,
 ) {
     if let Ok(_player_transform) = q_player.get_single() {
-        for (mut external_impulse, swarmling) in
+        for (mut external_impulse, swarmling, path) in
             q_swarmling.iter_mut()
         {
             match swarmling.state {
                 BehaviorState::Wander => {
                     external_impulse.x +=
-                        swarmling.path_noise.get_noise_2d(time.elapsed_secs(), 0.0)
-                            * SWARMLING_SPEED;
+                        path.noise.get_noise_2d(time.elapsed_secs(), 0.0)
+                            * balance.swarmling.speed;
                     external_impulse.y +=
-                        swarmling.path_noise.get_noise_2d(0.0, time.elapsed_secs())
-                            * SWARMLING_SPEED;
+                        path.noise.get_noise_2d(0.0, time.elapsed_secs())
+                            * balance.swarmling.speed;
                 }
                 BehaviorState::Flee => {}
                 BehaviorState::Attack => {}
@@ -137,15 +278,111 @@ pub fn swarmling_take_damage(
     //TODO
 }
 pub fn swarmling_death(
-    mut q_swarmling: Query<
-        (Entity, &Health, &mut EffectInitializers),
-        (With<Swarmling>, Changed<Health>),
-    >,
+    q_swarmling: Query<(Entity, &Health, &Transform, &Points), (With<Swarmling>, Changed<Health>)>,
+    mut spawn_effect_event_writer: EventWriter<SpawnEffect>,
+    mut swarmling_died_event_writer: EventWriter<SwarmlingDied>,
     mut commands: Commands,
 ) {
-    for (entity, health, mut initializers) in q_swarmling.iter_mut() {
+    for (entity, health, transform, points) in q_swarmling.iter() {
         if health.0 <= 0.1 {
-            initializers.reset();
+            let position = transform.translation.truncate();
+            spawn_effect_event_writer.send(SpawnEffect::new("swarmling_death", position));
+            swarmling_died_event_writer.send(SwarmlingDied {
+                entity,
+                position,
+                points: points.0,
+            });
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Tints hatcheries by [`HatcheryStage`] and speeds up spawning once they are critical.
+pub fn hatchery_damage(
+    mut q_hatchery: Query<(&mut Hatchery, &mut SwarmSpawner, &mut Sprite, &Health), Changed<Health>>,
+    balance: Res<GameBalance>,
+) {
+    for (mut hatchery, mut spawner, mut sprite, health) in q_hatchery.iter_mut() {
+        let stage = HatcheryStage::from_fraction(health.0 / hatchery.max_health, &balance.hatchery);
+        if stage == hatchery.stage {
+            continue;
+        }
+        hatchery.stage = stage;
+        sprite.color = stage.tint();
+        let interval = if stage == HatcheryStage::Critical {
+            hatchery.spawn_interval * balance.hatchery.panic_spawn_factor
+        } else {
+            hatchery.spawn_interval
+        };
+        spawner.spawn_timer.set_duration(Duration::from_secs_f32(interval));
+    }
+}
+
+pub fn hatchery_death(
+    q_hatchery: Query<(Entity, Ref<Health>, &Transform, &Points), (With<Hatchery>, Without<Dying>)>,
+    mut hatchery_destroyed_event_writer: EventWriter<HatcheryDestroyed>,
+    mut wave: ResMut<Wave>,
+    balance: Res<GameBalance>,
+    mut commands: Commands,
+) {
+    let dead: Vec<_> = q_hatchery
+        .iter()
+        .filter(|(_, health, _, _)| health.is_changed() && health.0 <= 0.1)
+        .collect();
+    // Counts down as each death is reported, so hatcheries dying in the same tick each
+    // see the ones after them as still standing
+    let mut remaining = q_hatchery
+        .iter()
+        .filter(|(_, health, _, _)| health.0 > 0.1)
+        .count()
+        + dead.len();
+    for (entity, _, transform, points) in dead {
+        remaining -= 1;
+        // The wreck stops being a physics body, so the shake in `hatchery_destruction`
+        // can't move a hitbox
+        commands
+            .entity(entity)
+            .insert(Dying {
+                timer: Timer::from_seconds(balance.hatchery.destruction_time, TimerMode::Once),
+            })
+            .remove::<(RigidBody, Collider)>();
+        hatchery_destroyed_event_writer.send(HatcheryDestroyed {
+            entity,
+            position: transform.translation.truncate(),
+            points: points.0,
+            remaining,
+        });
+        if remaining > 0 {
+            wave.0 += 1;
         }
     }
 }
+
+/// Shakes, shrinks and fades a dying hatchery, then despawns it. The level is won once
+/// the last one is gone.
+pub fn hatchery_destruction(
+    mut q_dying: Query<(Entity, &mut Dying, &mut Transform, &mut Sprite), With<Hatchery>>,
+    q_hatchery: Query<(), With<Hatchery>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let mut destroyed = 0;
+    for (entity, mut dying, mut transform, mut sprite) in q_dying.iter_mut() {
+        dying.timer.tick(time.delta());
+        if dying.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            destroyed += 1;
+            continue;
+        }
+        let progress = dying.timer.fraction();
+        let shake = Quat::from_rotation_z((fastrand::f32() - 0.5) * 0.3 * (1. - progress));
+        transform.rotation = shake;
+        transform.scale = Vec3::splat(1. - 0.5 * progress);
+        sprite.color = sprite.color.with_alpha(1. - progress);
+    }
+    if destroyed > 0 && q_hatchery.iter().count() == destroyed {
+        info!("All hatcheries destroyed");
+        next_state.set(AppState::Victory);
+    }
+}
@@ -0,0 +1,461 @@
+use bevy::prelude::*;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::arena::ArenaSource;
+use crate::bot::Autopilot;
+use crate::consts::*;
+use crate::player::{Player, PlayerDamageEvent, PlayerDeathEvent};
+use crate::projectile::{ProjectileFiredEvent, ProjectileHitEvent};
+use crate::replay::ReplayMode;
+use crate::save::RunLoaded;
+use crate::score::Score;
+use crate::swarm::Wave;
+use crate::weapon::Weapon;
+
+/// Seconds of real time covered by each frame time report.
+pub const FRAME_TIME_REPORT_INTERVAL: f32 = 30.0;
+
+/// Records a playtest session as one JSON object per line: the run's start and end, wave
+/// transitions, the player's death, weapon usage and frame time percentiles.
+///
+/// Off unless the game is started with `--telemetry`, which writes to a new
+/// `session-<unix time>.jsonl` in [`telemetry_dir`], with a `-<n>` suffix when another
+/// session started in the same second.
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Telemetry>()
+            .add_systems(Startup, start_telemetry)
+            .add_systems(
+                Update,
+                (record_run_start, record_gameplay, record_frame_times)
+                    .run_if(resource_exists::<TelemetryRecorder>),
+            )
+            .add_systems(
+                OnEnter(AppState::Victory),
+                record_run_end.run_if(resource_exists::<TelemetryRecorder>),
+            )
+            .add_systems(
+                OnEnter(AppState::GameOver),
+                record_run_end.run_if(resource_exists::<TelemetryRecorder>),
+            )
+            .add_systems(
+                Last,
+                finish_telemetry.run_if(resource_exists::<TelemetryRecorder>),
+            );
+    }
+}
+
+#[derive(Resource, Default, Clone, Debug)]
+pub enum Telemetry {
+    #[default]
+    Off,
+    /// Write session files to this directory
+    Record(PathBuf),
+}
+
+impl Telemetry {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        if !args.into_iter().any(|arg| arg == "--telemetry") {
+            return Telemetry::Off;
+        }
+        match telemetry_dir() {
+            Some(dir) => Telemetry::Record(dir),
+            None => {
+                warn!("No data directory to write telemetry to");
+                Telemetry::Off
+            }
+        }
+    }
+}
+
+/// `telemetry` in the user's data directory, if the platform has one.
+pub fn telemetry_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("swarmy").join("telemetry"))
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Victory,
+    GameOver,
+    /// The game was closed mid-run
+    Quit,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    SwarmlingCollision,
+    Unknown,
+}
+
+/// One line of a session file, tagged by `event`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    SessionStart {
+        version: String,
+    },
+    RunStart {
+        seed: u64,
+        arena: String,
+        bot: bool,
+        replay: bool,
+    },
+    Wave {
+        wave: u32,
+    },
+    Death {
+        cause: DeathCause,
+        position: Vec2,
+        wave: u32,
+    },
+    WeaponUsage {
+        weapon: String,
+        shots: u32,
+        hits: u32,
+        reloads: u32,
+    },
+    FrameTimes {
+        frames: usize,
+        p50_ms: f32,
+        p90_ms: f32,
+        p99_ms: f32,
+        max_ms: f32,
+    },
+    RunEnd {
+        outcome: RunOutcome,
+        duration: f32,
+        points: u64,
+        kills: u32,
+        hatcheries: u32,
+        best_combo: u32,
+        wave: u32,
+    },
+    SessionEnd,
+}
+
+#[derive(Serialize)]
+struct TelemetryLine<'a> {
+    /// Real seconds since the session started
+    time: f32,
+    #[serde(flatten)]
+    event: &'a TelemetryEvent,
+}
+
+/// The open session file and the tallies written at the end of the run.
+#[derive(Resource)]
+pub struct TelemetryRecorder {
+    path: PathBuf,
+    file: LineWriter<File>,
+    run_over: bool,
+    shots: u32,
+    hits: u32,
+    reloads: u32,
+    reloading: bool,
+    last_damage: Option<DeathCause>,
+    frame_times: Vec<f32>,
+    report_elapsed: f32,
+}
+
+impl TelemetryRecorder {
+    fn create(dir: &Path) -> Result<Self, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let mut attempt = 0;
+        let (path, file) = loop {
+            let name = if attempt == 0 {
+                format!("session-{}.jsonl", timestamp)
+            } else {
+                format!("session-{}-{}.jsonl", timestamp, attempt)
+            };
+            let path = dir.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => attempt += 1,
+                Err(err) => return Err(err.to_string()),
+            }
+        };
+        Ok(TelemetryRecorder {
+            path,
+            file: LineWriter::new(file),
+            run_over: false,
+            shots: 0,
+            hits: 0,
+            reloads: 0,
+            reloading: false,
+            last_damage: None,
+            frame_times: Vec::new(),
+            report_elapsed: 0.,
+        })
+    }
+
+    /// Writes `event` as a line, flushed right away so a crash keeps everything before it.
+    pub fn write(&mut self, time: f32, event: TelemetryEvent) {
+        let line = TelemetryLine {
+            time,
+            event: &event,
+        };
+        let result = serde_json::to_string(&line)
+            .map_err(|e| e.to_string())
+            .and_then(|json| writeln!(self.file, "{}", json).map_err(|e| e.to_string()));
+        if let Err(err) = result {
+            warn!(
+                "Could not write telemetry to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+
+    /// Writes `event`, a [`TelemetryEvent::RunStart`], and clears the previous run's tallies.
+    fn start_run(&mut self, time: f32, event: TelemetryEvent) {
+        self.run_over = false;
+        self.shots = 0;
+        self.hits = 0;
+        self.reloads = 0;
+        self.reloading = false;
+        self.last_damage = None;
+        self.write(time, event);
+    }
+
+    fn frame_time_report(&mut self) -> Option<TelemetryEvent> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+        let mut frame_times = std::mem::take(&mut self.frame_times);
+        frame_times.sort_by(f32::total_cmp);
+        let percentile = |p: f32| {
+            let index = ((frame_times.len() - 1) as f32 * p).round() as usize;
+            frame_times[index] * 1000.
+        };
+        Some(TelemetryEvent::FrameTimes {
+            frames: frame_times.len(),
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: percentile(1.),
+        })
+    }
+
+    fn end_run(
+        &mut self,
+        time: f32,
+        outcome: RunOutcome,
+        score: &Score,
+        wave: u32,
+        weapon: String,
+    ) {
+        self.run_over = true;
+        self.write(
+            time,
+            TelemetryEvent::WeaponUsage {
+                weapon,
+                shots: self.shots,
+                hits: self.hits,
+                reloads: self.reloads,
+            },
+        );
+        self.write(
+            time,
+            TelemetryEvent::RunEnd {
+                outcome,
+                duration: score.duration,
+                points: score.points,
+                kills: score.kills,
+                hatcheries: score.hatcheries,
+                best_combo: score.best_combo,
+                wave,
+            },
+        );
+    }
+}
+
+fn start_telemetry(
+    mut commands: Commands,
+    telemetry: Res<Telemetry>,
+    rng: Res<GameRng>,
+    arena: Res<ArenaSource>,
+    autopilot: Res<Autopilot>,
+    replay: Option<Res<ReplayMode>>,
+    time: Res<Time<Real>>,
+) {
+    let Telemetry::Record(dir) = telemetry.as_ref() else {
+        return;
+    };
+    let mut recorder = match TelemetryRecorder::create(dir) {
+        Ok(recorder) => recorder,
+        Err(err) => {
+            warn!("Could not start telemetry in {}: {}", dir.display(), err);
+            return;
+        }
+    };
+    info!("Recording telemetry to {}", recorder.path.display());
+    let now = time.elapsed_secs();
+    recorder.write(
+        now,
+        TelemetryEvent::SessionStart {
+            version: String::from(env!("CARGO_PKG_VERSION")),
+        },
+    );
+    recorder.start_run(now, run_start(&rng, &arena, &autopilot, replay.as_deref()));
+    commands.insert_resource(recorder);
+}
+
+fn run_start(
+    rng: &GameRng,
+    arena: &ArenaSource,
+    autopilot: &Autopilot,
+    replay: Option<&ReplayMode>,
+) -> TelemetryEvent {
+    TelemetryEvent::RunStart {
+        seed: rng.seed,
+        arena: format!("{:?}", arena),
+        bot: autopilot.enabled,
+        replay: replay.is_some_and(|mode| matches!(mode, ReplayMode::Playback(_))),
+    }
+}
+
+/// Starts a new run when a saved one is loaded. The run it replaced gets no `run_end`,
+/// since its score was overwritten by the load.
+fn record_run_start(
+    mut recorder: ResMut<TelemetryRecorder>,
+    mut run_loaded_event_reader: EventReader<RunLoaded>,
+    rng: Res<GameRng>,
+    arena: Res<ArenaSource>,
+    autopilot: Res<Autopilot>,
+    replay: Option<Res<ReplayMode>>,
+    time: Res<Time<Real>>,
+) {
+    if run_loaded_event_reader.read().count() == 0 {
+        return;
+    }
+    recorder.start_run(
+        time.elapsed_secs(),
+        run_start(&rng, &arena, &autopilot, replay.as_deref()),
+    );
+}
+
+fn record_gameplay(
+    mut recorder: ResMut<TelemetryRecorder>,
+    mut player_damage_event_reader: EventReader<PlayerDamageEvent>,
+    mut player_death_event_reader: EventReader<PlayerDeathEvent>,
+    mut projectile_fired_event_reader: EventReader<ProjectileFiredEvent>,
+    mut projectile_hit_event_reader: EventReader<ProjectileHitEvent>,
+    q_player: Query<(&Transform, &Weapon), With<Player>>,
+    wave: Res<Wave>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_secs();
+    if wave.is_changed() && !wave.is_added() {
+        recorder.write(now, TelemetryEvent::Wave { wave: wave.0 });
+    }
+
+    recorder.shots += projectile_fired_event_reader.read().count() as u32;
+    recorder.hits += projectile_hit_event_reader.read().count() as u32;
+    for event in player_damage_event_reader.read() {
+        recorder.last_damage = Some(match event {
+            PlayerDamageEvent::Collision(_) => DeathCause::SwarmlingCollision,
+        });
+    }
+
+    let player = q_player.get_single().ok();
+    if let Some((_, weapon)) = player {
+        let reloading = weapon.reload_progress().is_some();
+        if reloading && !recorder.reloading {
+            recorder.reloads += 1;
+        }
+        recorder.reloading = reloading;
+    }
+    for _ in player_death_event_reader.read() {
+        let position = player.map_or(Vec2::ZERO, |(transform, _)| {
+            transform.translation.truncate()
+        });
+        let cause = recorder.last_damage.unwrap_or(DeathCause::Unknown);
+        recorder.write(
+            now,
+            TelemetryEvent::Death {
+                cause,
+                position,
+                wave: wave.0,
+            },
+        );
+    }
+}
+
+fn record_frame_times(mut recorder: ResMut<TelemetryRecorder>, time: Res<Time<Real>>) {
+    recorder.frame_times.push(time.delta_secs());
+    recorder.report_elapsed += time.delta_secs();
+    if recorder.report_elapsed >= FRAME_TIME_REPORT_INTERVAL {
+        recorder.report_elapsed = 0.;
+        if let Some(report) = recorder.frame_time_report() {
+            recorder.write(time.elapsed_secs(), report);
+        }
+    }
+}
+
+fn weapon_name(q_weapon: &Query<&Weapon, With<Player>>) -> String {
+    q_weapon
+        .get_single()
+        .map_or(String::new(), |weapon| weapon.name.clone())
+}
+
+fn record_run_end(
+    mut recorder: ResMut<TelemetryRecorder>,
+    state: Res<State<AppState>>,
+    score: Res<Score>,
+    wave: Res<Wave>,
+    q_weapon: Query<&Weapon, With<Player>>,
+    time: Res<Time<Real>>,
+) {
+    let outcome = if *state.get() == AppState::Victory {
+        RunOutcome::Victory
+    } else {
+        RunOutcome::GameOver
+    };
+    recorder.end_run(
+        time.elapsed_secs(),
+        outcome,
+        &score,
+        wave.0,
+        weapon_name(&q_weapon),
+    );
+}
+
+/// Closes the session when the app exits, ending the run first if it was still going.
+fn finish_telemetry(
+    mut commands: Commands,
+    mut recorder: ResMut<TelemetryRecorder>,
+    mut app_exit_event_reader: EventReader<AppExit>,
+    score: Res<Score>,
+    wave: Res<Wave>,
+    q_weapon: Query<&Weapon, With<Player>>,
+    time: Res<Time<Real>>,
+) {
+    if app_exit_event_reader.read().next().is_none() {
+        return;
+    }
+    let now = time.elapsed_secs();
+    if !recorder.run_over {
+        recorder.end_run(
+            now,
+            RunOutcome::Quit,
+            &score,
+            wave.0,
+            weapon_name(&q_weapon),
+        );
+    }
+    if let Some(report) = recorder.frame_time_report() {
+        recorder.write(now, report);
+    }
+    recorder.write(now, TelemetryEvent::SessionEnd);
+    commands.remove_resource::<TelemetryRecorder>();
+}
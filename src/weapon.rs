@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::balance::WeaponBalance;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Weapon {
+    pub name: String,
+    /// Maximum deviation from the aim direction, in radians.
+    pub spread: f32,
+    pub magazine_size: u32,
+    pub ammo: u32,
+    pub reload_time: f32,
+    reload_elapsed: Option<f32>,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon::new(&WeaponBalance::default())
+    }
+}
+
+impl Weapon {
+    pub fn new(balance: &WeaponBalance) -> Self {
+        Weapon {
+            name: String::from("Blaster"),
+            spread: balance.spread,
+            magazine_size: balance.magazine_size,
+            ammo: balance.magazine_size,
+            reload_time: balance.reload_time,
+            reload_elapsed: None,
+        }
+    }
+
+    /// Takes on new stats, keeping the rounds already loaded.
+    pub fn retune(&mut self, balance: &WeaponBalance) {
+        self.spread = balance.spread;
+        self.magazine_size = balance.magazine_size;
+        self.ammo = self.ammo.min(balance.magazine_size);
+        self.reload_time = balance.reload_time;
+    }
+
+    pub fn can_fire(&self) -> bool {
+        self.reload_elapsed.is_none() && self.ammo > 0
+    }
+
+    /// Uses up one round, starting a reload when the magazine runs empty.
+    pub fn fire(&mut self) {
+        self.ammo = self.ammo.saturating_sub(1);
+        if self.ammo == 0 {
+            self.reload_elapsed = Some(0.0);
+        }
+    }
+
+    /// Reload progress from 0 to 1, or `None` while not reloading.
+    pub fn reload_progress(&self) -> Option<f32> {
+        self.reload_elapsed
+            .map(|elapsed| (elapsed / self.reload_time).clamp(0.0, 1.0))
+    }
+}
+
+pub fn weapon_reload(mut q_weapon: Query<&mut Weapon>, time: Res<Time>) {
+    for mut weapon in q_weapon.iter_mut() {
+        if let Some(elapsed) = weapon.reload_elapsed {
+            let elapsed = elapsed + time.delta_secs();
+            if elapsed >= weapon.reload_time {
+                weapon.reload_elapsed = None;
+                weapon.ammo = weapon.magazine_size;
+            } else {
+                weapon.reload_elapsed = Some(elapsed);
+            }
+        }
+    }
+}
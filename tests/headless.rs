@@ -0,0 +1,307 @@
+use bevy::prelude::*;
+use swarmy::arena::ArenaSource;
+use swarmy::balance::GameBalance;
+use swarmy::consts::*;
+use swarmy::input::{InputBuffer, PlayerAimEvent, PlayerAttackEvent, PlayerMovementEvent};
+use swarmy::player::Player;
+use swarmy::projectile::{projectile_collision, Projectile, ProjectileHitEvent};
+use swarmy::replay::{ReplayMode, ReplayPlugin};
+use swarmy::save::RunLoaded;
+use swarmy::swarm::*;
+use swarmy::telemetry::Telemetry;
+use swarmy::weapon::Weapon;
+
+/// Fixed ticks per simulated second, matching Bevy's default fixed timestep.
+const TICKS_PER_SECOND: usize = 64;
+
+fn test_app() -> App {
+    let mut app = swarmy::headless_app(42);
+    app.insert_resource(ArenaSource::Empty);
+    // Runs the startup schedule, which spawns the player at the origin
+    app.update();
+    app
+}
+
+fn step(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+fn spawn_hatchery(app: &mut App, position: Vec2) -> Entity {
+    let assets = app.world().resource::<AssetServer>().clone();
+    let balance = app.world().resource::<GameBalance>().hatchery.clone();
+    app.world_mut()
+        .spawn(hatchery_bundle(&assets, position, HatcherySettings::default(), &balance))
+        .id()
+}
+
+fn player_position(app: &mut App) -> Vec2 {
+    app.world_mut()
+        .query_filtered::<&Transform, With<Player>>()
+        .single(app.world())
+        .translation
+        .truncate()
+}
+
+#[derive(Resource, Default)]
+struct HitLog(Vec<Entity>);
+
+fn log_hits(mut hit_log: ResMut<HitLog>, mut projectile_hit_event_reader: EventReader<ProjectileHitEvent>) {
+    hit_log.0.extend(projectile_hit_event_reader.read().map(|hit| hit.target));
+}
+
+#[test]
+fn hatchery_spawns_swarmlings() {
+    let mut app = test_app();
+    spawn_hatchery(&mut app, Vec2::new(0., 300.));
+
+    step(&mut app, 3 * TICKS_PER_SECOND);
+
+    let swarmlings = app
+        .world_mut()
+        .query_filtered::<(), With<Swarmling>>()
+        .iter(app.world())
+        .count();
+    assert!(swarmlings >= 2, "expected swarmlings after 3s, found {}", swarmlings);
+}
+
+#[test]
+fn projectile_damages_hatchery() {
+    let mut app = test_app();
+    app.init_resource::<HitLog>()
+        .add_systems(FixedUpdate, log_hits.after(projectile_collision));
+    let hatchery = spawn_hatchery(&mut app, Vec2::new(300., 0.));
+    let balance = app.world().resource::<GameBalance>().clone();
+    app.world_mut().spawn((
+        Projectile::new(&balance.projectile),
+        Transform::from_xyz(200., 0., 0.),
+    ));
+
+    step(&mut app, TICKS_PER_SECOND / 2);
+
+    let health = app.world().get::<Health>(hatchery).unwrap();
+    assert!(health.0 < balance.hatchery.health, "hatchery health is still {}", health.0);
+    assert!(app.world().resource::<HitLog>().0.contains(&hatchery));
+}
+
+#[derive(Resource, Default)]
+struct DestroyedLog(Vec<usize>);
+
+fn log_destroyed(
+    mut destroyed_log: ResMut<DestroyedLog>,
+    mut hatchery_destroyed_event_reader: EventReader<HatcheryDestroyed>,
+) {
+    destroyed_log
+        .0
+        .extend(hatchery_destroyed_event_reader.read().map(|destroyed| destroyed.remaining));
+}
+
+#[test]
+fn hatcheries_dying_together_count_down() {
+    let mut app = test_app();
+    app.init_resource::<DestroyedLog>()
+        .add_systems(FixedUpdate, log_destroyed.after(hatchery_death));
+    let hatcheries = [
+        spawn_hatchery(&mut app, Vec2::new(300., 0.)),
+        spawn_hatchery(&mut app, Vec2::new(-300., 0.)),
+        spawn_hatchery(&mut app, Vec2::new(0., 300.)),
+    ];
+    step(&mut app, 1);
+
+    for hatchery in &hatcheries[..2] {
+        app.world_mut().get_mut::<Health>(*hatchery).unwrap().0 = 0.;
+    }
+    step(&mut app, 1);
+
+    assert_eq!(app.world().resource::<DestroyedLog>().0, [2, 1]);
+}
+
+#[test]
+fn primary_fire_spends_ammo() {
+    let mut app = test_app();
+
+    app.world_mut().send_event(PlayerAttackEvent::PrimaryFire);
+    app.world_mut().send_event(PlayerAttackEvent::SecondaryFire);
+    step(&mut app, 2);
+
+    // Secondary fire does nothing yet, but its press is used up rather than left buffered
+    let mut input_buffer = app.world_mut().resource_mut::<InputBuffer>();
+    assert!(input_buffer.take(|_| true).is_none());
+    let weapon = app
+        .world_mut()
+        .query_filtered::<&Weapon, With<Player>>()
+        .single(app.world());
+    assert_eq!(weapon.ammo, weapon.magazine_size - 1);
+    let projectiles = app
+        .world_mut()
+        .query_filtered::<(), With<Projectile>>()
+        .iter(app.world())
+        .count();
+    assert_eq!(projectiles, 1);
+}
+
+#[test]
+fn dash_moves_player() {
+    let mut app = test_app();
+    let balance = app.world().resource::<GameBalance>().player.clone();
+    // The dash starts out on cooldown
+    step(&mut app, (balance.dash_cooldown * TICKS_PER_SECOND as f32) as usize + 2);
+    let start = player_position(&mut app);
+
+    app.world_mut().send_event(PlayerMovementEvent::Dash(Vec2::X));
+    step(&mut app, (balance.dash_duration * TICKS_PER_SECOND as f32) as usize);
+
+    let end = player_position(&mut app);
+    assert!(end.x - start.x > 50., "player only moved from {} to {}", start, end);
+}
+
+/// Runs a telemetry session for `ticks`, calling `during` halfway, and returns its events.
+fn record_session(
+    name: &str,
+    ticks: usize,
+    mut during: impl FnMut(&mut App),
+) -> Vec<serde_json::Value> {
+    let dir = std::env::temp_dir().join(format!("swarmy-{}-{}", name, std::process::id()));
+    let mut app = swarmy::headless_app(42);
+    app.insert_resource(ArenaSource::Empty)
+        .insert_resource(Telemetry::Record(dir.clone()));
+    step(&mut app, ticks / 2);
+    during(&mut app);
+    step(&mut app, ticks - ticks / 2);
+    app.world_mut().send_event(AppExit::Success);
+    app.update();
+
+    let session = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|extension| extension == "jsonl"))
+        .expect("no session file");
+    let contents = std::fs::read_to_string(&session).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn event_names(events: &[serde_json::Value]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn telemetry_records_session() {
+    let events = record_session("telemetry", 10, |_| {});
+    assert_eq!(
+        event_names(&events),
+        ["session_start", "run_start", "weapon_usage", "run_end", "frame_times", "session_end"]
+    );
+    assert_eq!(events[1]["seed"], 42);
+    assert_eq!(events[3]["outcome"], "quit");
+}
+
+#[test]
+fn telemetry_sessions_get_their_own_files() {
+    let dir = std::env::temp_dir().join(format!("swarmy-telemetry-pair-{}", std::process::id()));
+    let mut apps: Vec<App> = (0..2)
+        .map(|_| {
+            let mut app = swarmy::headless_app(42);
+            app.insert_resource(ArenaSource::Empty)
+                .insert_resource(Telemetry::Record(dir.clone()));
+            app.update();
+            app
+        })
+        .collect();
+    for app in apps.iter_mut() {
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+    }
+
+    let sessions = std::fs::read_dir(&dir).unwrap().count();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(sessions, 2);
+}
+
+#[test]
+fn telemetry_starts_a_run_on_load() {
+    let events = record_session("telemetry-load", 10, |app| {
+        app.world_mut().send_event(RunLoaded);
+    });
+    assert_eq!(
+        event_names(&events),
+        [
+            "session_start",
+            "run_start",
+            "run_start",
+            "weapon_usage",
+            "run_end",
+            "frame_times",
+            "session_end"
+        ]
+    );
+    assert_eq!(events[4]["outcome"], "quit");
+}
+
+/// Circles while aiming off to the side, firing every few ticks and dashing now and then.
+fn scripted_input(app: &mut App, tick: usize) {
+    let world = app.world_mut();
+    let heading = Vec2::from_angle(tick as f32 * 0.02);
+    world.send_event(PlayerMovementEvent::Move(heading));
+    world.send_event(PlayerAimEvent(heading.perp()));
+    if tick % 8 == 0 {
+        world.send_event(PlayerAttackEvent::PrimaryFire);
+    }
+    if tick % 90 == 45 {
+        world.send_event(PlayerMovementEvent::Dash(heading));
+    }
+}
+
+/// Runs `ticks` on a generated arena under `mode`, feeding `input` before each one, and
+/// returns the final player, hatchery and swarmling transforms and the RNG state.
+fn replay_run(
+    mode: ReplayMode,
+    ticks: usize,
+    mut input: impl FnMut(&mut App, usize),
+) -> (Vec<(Entity, Transform)>, u64, usize) {
+    let mut app = swarmy::headless_app(42);
+    app.insert_resource(mode).add_plugins(ReplayPlugin);
+    app.update();
+    for tick in 0..ticks {
+        input(&mut app, tick);
+        app.update();
+    }
+    app.world_mut().send_event(AppExit::Success);
+    app.update();
+
+    let mut transforms: Vec<(Entity, Transform)> = app
+        .world_mut()
+        .query_filtered::<(Entity, &Transform), Or<(With<Player>, With<Hatchery>, With<Swarmling>)>>()
+        .iter(app.world())
+        .map(|(entity, transform)| (entity, *transform))
+        .collect();
+    transforms.sort_by_key(|(entity, _)| *entity);
+    let swarmlings = app
+        .world_mut()
+        .query_filtered::<(), With<Swarmling>>()
+        .iter(app.world())
+        .count();
+    (transforms, app.world().resource::<GameRng>().state(), swarmlings)
+}
+
+#[test]
+fn replay_reproduces_recorded_run() {
+    let path = std::env::temp_dir().join(format!("swarmy-replay-{}.ron", std::process::id()));
+    let ticks = 6 * TICKS_PER_SECOND;
+
+    let (recorded, recorded_rng, swarmlings) =
+        replay_run(ReplayMode::Record(path.clone()), ticks, scripted_input);
+    let (played, played_rng, _) = replay_run(ReplayMode::Playback(path.clone()), ticks, |_, _| {});
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(swarmlings > 0, "no swarmlings spawned, so the run proves little");
+    assert_eq!(recorded, played);
+    assert_eq!(recorded_rng, played_rng);
+}